@@ -0,0 +1,229 @@
+/// The two sides of a failed `assert_eq!` or `assert_ne!`, as printed by
+/// their `Debug` impls.
+#[derive(Debug, PartialEq)]
+pub struct Assertion<'a> {
+    /// `"assert_eq"` or `"assert_ne"`.
+    pub kind: &'a str,
+    pub left: &'a str,
+    pub right: &'a str,
+    /// The custom message passed after the two values, if any.
+    pub message: Option<&'a str>,
+}
+
+fn kind_of(op: &str) -> &'static str {
+    if op == "==" { "assert_eq" } else { "assert_ne" }
+}
+
+fn non_empty(x: &str) -> Option<&str> {
+    let x = x.trim();
+
+    if x.is_empty() { None } else { Some(x) }
+}
+
+// Since 1.73:
+//
+//     assertion `left == right` failed: message
+//       left: 1
+//      right: 2
+fn parse_current(error: &str) -> Option<Assertion<'_>> {
+    for &op in &["==", "!="] {
+        let head = format!("assertion `left {} right` failed", op);
+
+        let start = match error.find(&head) {
+            Some(i) => i + head.len(),
+            None => continue,
+        };
+
+        let rest = &error[start..];
+        let left_at = rest.find("\n  left: ")?;
+        let right_at = rest.find("\n right: ")?;
+
+        if right_at < left_at {
+            return None;
+        }
+
+        let message = rest[..left_at].trim_start_matches(':');
+        let left = &rest[left_at + "\n  left: ".len()..right_at];
+        let right = rest[right_at + "\n right: ".len()..].lines().next().unwrap_or("");
+
+        return Some(Assertion {
+            kind: kind_of(op),
+            left: left.trim(),
+            right: right.trim(),
+            message: non_empty(message),
+        });
+    }
+
+    None
+}
+
+// From 1.20 until 1.73, inside the quoted panic message:
+//
+//     assertion failed: `(left == right)`
+//       left: `1`,
+//      right: `2`: message
+fn parse_multi_line(error: &str) -> Option<Assertion<'_>> {
+    for &op in &["==", "!="] {
+        let head = format!("assertion failed: `(left {} right)`", op);
+
+        let start = match error.find(&head) {
+            Some(i) => i + head.len(),
+            None => continue,
+        };
+
+        let rest = &error[start..];
+
+        if !rest.starts_with("\n  left: `") {
+            continue;
+        }
+
+        let rest = &rest["\n  left: `".len()..];
+        let right_at = rest.find("`,\n right: `")?;
+        let left = &rest[..right_at];
+
+        let rest = &rest[right_at + "`,\n right: `".len()..];
+        // The panic message is quoted and followed by its location.
+        let rest = match rest.rfind("', ") {
+            Some(i) => &rest[..i],
+            None => rest,
+        };
+
+        let (right, message) = match rest.find("`: ") {
+            Some(i) => (&rest[..i], non_empty(&rest[i + "`: ".len()..])),
+            None => (rest.trim_end().trim_end_matches('`'), None),
+        };
+
+        return Some(Assertion {
+            kind: kind_of(op),
+            left,
+            right,
+            message,
+        });
+    }
+
+    None
+}
+
+// Before 1.20, all on one line:
+//
+//     assertion failed: `(left == right)` (left: `1`, right: `2`)
+fn parse_single_line(error: &str) -> Option<Assertion<'_>> {
+    for &op in &["==", "!="] {
+        let head = format!("assertion failed: `(left {} right)` (left: `", op);
+
+        let start = match error.find(&head) {
+            Some(i) => i + head.len(),
+            None => continue,
+        };
+
+        let rest = &error[start..];
+        let right_at = rest.find("`, right: `")?;
+        let left = &rest[..right_at];
+
+        let rest = &rest[right_at + "`, right: `".len()..];
+        let end = rest.find("`)")?;
+
+        let message = rest[end + "`)".len()..]
+            .split("', ")
+            .next()
+            .unwrap_or("")
+            .trim_start_matches(':');
+
+        return Some(Assertion {
+            kind: kind_of(op),
+            left,
+            right: &rest[..end],
+            message: non_empty(message),
+        });
+    }
+
+    None
+}
+
+/// Picks the failed assertion out of a panic message, if it was one.
+pub fn parse_assertion(error: &str) -> Option<Assertion<'_>> {
+    parse_current(error)
+        .or_else(|| parse_multi_line(error))
+        .or_else(|| parse_single_line(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_assertion, Assertion};
+
+    #[test]
+    fn it_should_parse_a_single_line_assertion() {
+        assert_eq!(
+            parse_assertion(
+                "thread 'fail' panicked at 'assertion failed: `(left == right)` \
+                 (left: `1`, right: `2`)', tests/integration_test.rs:16"
+            ),
+            Some(Assertion {
+                kind: "assert_eq",
+                left: "1",
+                right: "2",
+                message: None,
+            })
+        );
+    }
+
+    #[test]
+    fn it_should_parse_a_multi_line_assertion_with_a_message() {
+        assert_eq!(
+            parse_assertion(
+                "thread 'tests::it_fails' panicked at 'assertion failed: `(left != right)`
+  left: `\"a\"`,
+ right: `\"a\"`: names should differ', src/lib.rs:12:9"
+            ),
+            Some(Assertion {
+                kind: "assert_ne",
+                left: "\"a\"",
+                right: "\"a\"",
+                message: Some("names should differ"),
+            })
+        );
+    }
+
+    #[test]
+    fn it_should_parse_a_multi_line_assertion() {
+        assert_eq!(
+            parse_assertion(
+                "thread 'tests::it_fails' panicked at 'assertion failed: `(left == right)`
+  left: `[1, 2]`,
+ right: `[1, 3]`', src/lib.rs:12:9"
+            ),
+            Some(Assertion {
+                kind: "assert_eq",
+                left: "[1, 2]",
+                right: "[1, 3]",
+                message: None,
+            })
+        );
+    }
+
+    #[test]
+    fn it_should_parse_a_current_assertion() {
+        assert_eq!(
+            parse_assertion(
+                "thread 'tests::it_fails' panicked at src/lib.rs:6:9:
+assertion `left == right` failed: totals differ
+  left: Some(1)
+ right: None"
+            ),
+            Some(Assertion {
+                kind: "assert_eq",
+                left: "Some(1)",
+                right: "None",
+                message: Some("totals differ"),
+            })
+        );
+    }
+
+    #[test]
+    fn it_should_not_parse_other_panics() {
+        assert_eq!(
+            parse_assertion("thread 'tests::it_fails' panicked at 'assertion failed: x > 3', src/lib.rs:6"),
+            None
+        );
+    }
+}
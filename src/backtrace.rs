@@ -0,0 +1,216 @@
+use super::{parse_source_location, Test};
+
+/// One frame of a `stack backtrace:` printed with `RUST_BACKTRACE` set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame<'a> {
+    pub index: i64,
+    pub symbol: &'a str,
+    pub file: Option<&'a str>,
+    pub line: Option<i64>,
+}
+
+const INTERNAL_CRATES: &[&str] = &["std", "core", "alloc", "test", "backtrace", "panic_unwind"];
+
+// Symbols from the runtime and libc, which user code can't share a name with
+// without clashing. Prefixes cover `__rust_begin_short_backtrace`,
+// `__libc_start_main` and the like.
+const INTERNAL_PREFIXES: &[&str] = &["__rust", "__libc_start"];
+const INTERNAL_SYMBOLS: &[&str] = &[
+    "rust_begin_unwind",
+    "_start",
+    "start_thread",
+    "clone",
+    "clone3",
+    "__clone",
+    "__clone3",
+];
+
+impl<'a> Frame<'a> {
+    /// Whether the frame belongs to std, core or the test harness rather
+    /// than the code under test.
+    pub fn is_internal(&self) -> bool {
+        if let Some(file) = self.file {
+            if file.starts_with("/rustc/") || file.contains("/checkout/src/") {
+                return true;
+            }
+        }
+
+        // `<T as Trait>::method` belongs to whoever owns `T`.
+        let symbol = match (self.symbol.starts_with('<'), self.symbol.find(" as ")) {
+            (true, Some(i)) => &self.symbol[1..i],
+            _ => self.symbol,
+        };
+
+        let is_internal_crate = INTERNAL_CRATES
+            .iter()
+            .any(|x| symbol.starts_with(x) && symbol[x.len()..].starts_with("::"));
+
+        symbol.starts_with("fn(") || symbol == "F" || is_internal_crate ||
+            INTERNAL_PREFIXES.iter().any(|x| symbol.starts_with(x)) || INTERNAL_SYMBOLS.contains(&symbol)
+    }
+}
+
+impl<'a, 'b, 'c> Test<'a, 'b, 'c> {
+    /// The backtrace without the frames from std, core and the test harness.
+    pub fn user_frames(&self) -> Vec<&Frame<'c>> {
+        self.backtrace.iter().filter(|x| !x.is_internal()).collect()
+    }
+}
+
+// `  4: foo::tests::it_fails`, or `  4:   0x55d0c0d5a5b3 - foo::tests::it_fails`
+// with `RUST_BACKTRACE=full`.
+fn parse_frame(line: &str) -> Option<(i64, &str)> {
+    let line = line.trim();
+    let i = line.find(':')?;
+    let index = line[..i].parse().ok()?;
+    let symbol = line[i + 1..].trim();
+
+    let symbol = match symbol.find(" - ") {
+        Some(j) if symbol.starts_with("0x") => &symbol[j + " - ".len()..],
+        _ => symbol,
+    };
+
+    Some((index, symbol))
+}
+
+/// Splits a failure message from the backtrace printed after it.
+pub fn split_backtrace(text: &str) -> (&str, Vec<Frame<'_>>) {
+    let start = match text.find("stack backtrace:") {
+        Some(i) if i == 0 || text[..i].ends_with('\n') => i,
+        _ => return (text, vec![]),
+    };
+
+    let mut frames: Vec<Frame> = vec![];
+
+    for line in text[start..].lines().skip(1) {
+        if let Some((index, symbol)) = parse_frame(line) {
+            frames.push(Frame {
+                index,
+                symbol,
+                file: None,
+                line: None,
+            });
+            continue;
+        }
+
+        let location = match (line.trim().starts_with("at "), frames.last_mut()) {
+            (true, Some(frame)) => Some(frame),
+            _ => None,
+        };
+
+        // Anything else, like the note about `RUST_BACKTRACE=full`, ends it.
+        let frame = match location {
+            Some(x) => x,
+            None => break,
+        };
+
+        let at = &line.trim()["at ".len()..];
+
+        match parse_source_location(at) {
+            Some(x) => {
+                frame.file = Some(&at[..x.file.len()]);
+                frame.line = Some(x.line);
+            }
+            None => frame.file = Some(at),
+        }
+    }
+
+    (text[..start].trim_end(), frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_backtrace, Frame};
+
+    #[test]
+    fn it_should_split_a_backtrace_from_the_message() {
+        let (message, frames) = split_backtrace(
+            "thread 'tests::it_fails' panicked at src/lib.rs:6:18:
+boom
+stack backtrace:
+   0: __rustc::rust_begin_unwind
+             at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/std/src/panicking.rs:689:5
+   1: core::panicking::panic_fmt
+             at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/core/src/panicking.rs:80:14
+   2: foo::tests::it_fails
+             at ./src/lib.rs:6:18
+   3: <fn() -> core::result::Result<(), alloc::string::String> as core::ops::function::FnOnce<()>>::call_once
+             at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/core/src/ops/function.rs:250:5
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.",
+        );
+
+        assert_eq!(message, "thread 'tests::it_fails' panicked at src/lib.rs:6:18:\nboom");
+        assert_eq!(frames.len(), 4);
+        assert_eq!(
+            frames[2],
+            Frame {
+                index: 2,
+                symbol: "foo::tests::it_fails",
+                file: Some("./src/lib.rs"),
+                line: Some(6),
+            }
+        );
+
+        let internal: Vec<bool> = frames.iter().map(|x| x.is_internal()).collect();
+        assert_eq!(internal, vec![true, true, false, true]);
+    }
+
+    #[test]
+    fn it_should_parse_full_and_old_frames() {
+        let (_, frames) = split_backtrace(
+            "thread 'it_fails' panicked at 'boom', tests/integration_test.rs:16
+stack backtrace:
+   0:     0x55d0c0d5a5b3 - std::sys::imp::backtrace::tracing::imp::unwind_backtrace::h0a6e7a3fcd3d3a5f
+                        at /checkout/src/libstd/sys/unix/backtrace/tracing/gcc_s.rs:49
+   1:     0x55d0c0d5a5c4 - integration_test::it_fails::h2b1f5e2bd6b0c8a1
+                        at tests/integration_test.rs:16
+   2:     0x7f1e5a8c06da - start_thread",
+        );
+
+        assert_eq!(
+            frames,
+            vec![
+                Frame {
+                    index: 0,
+                    symbol: "std::sys::imp::backtrace::tracing::imp::unwind_backtrace::h0a6e7a3fcd3d3a5f",
+                    file: Some("/checkout/src/libstd/sys/unix/backtrace/tracing/gcc_s.rs"),
+                    line: Some(49),
+                },
+                Frame {
+                    index: 1,
+                    symbol: "integration_test::it_fails::h2b1f5e2bd6b0c8a1",
+                    file: Some("tests/integration_test.rs"),
+                    line: Some(16),
+                },
+                Frame {
+                    index: 2,
+                    symbol: "start_thread",
+                    file: None,
+                    line: None,
+                },
+            ]
+        );
+
+        let user: Vec<i64> = frames.iter().filter(|x| !x.is_internal()).map(|x| x.index).collect();
+        assert_eq!(user, vec![1]);
+    }
+
+    #[test]
+    fn it_should_keep_user_crates_named_like_internal_ones() {
+        let frame = |symbol| Frame {
+            index: 0,
+            symbol,
+            file: None,
+            line: None,
+        };
+
+        assert!(!frame("cloner::tests::it_fails").is_internal());
+        assert!(!frame("start_thread_pool::tests::it_fails").is_internal());
+        assert!(!frame("tester::it_fails").is_internal());
+        assert!(!frame("<cloner::Sheep as core::clone::Clone>::clone").is_internal());
+
+        assert!(frame("clone").is_internal());
+        assert!(frame("__libc_start_main").is_internal());
+        assert!(frame("test::run_test::{{closure}}").is_internal());
+    }
+}
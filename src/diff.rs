@@ -0,0 +1,275 @@
+//! Compares two runs, as in "what changed since main?".
+
+use std::collections::HashMap;
+
+use super::{Test, TestRun};
+
+/// A test is only reported as slower when it took at least this many times
+/// as long as before...
+const SLOWDOWN_RATIO: f64 = 1.5;
+/// ...and at least this many seconds longer, so that noise in very fast tests
+/// doesn't show up.
+const SLOWDOWN_SECONDS: f64 = 0.1;
+
+#[derive(Debug, PartialEq)]
+pub struct TestId<'a> {
+    pub suite: &'a str,
+    pub name: &'a str,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DurationChange<'a> {
+    pub test: TestId<'a>,
+    pub old: f64,
+    pub new: f64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RunDiff<'a> {
+    /// Tests that fail now but didn't before, including new tests that fail.
+    pub newly_failing: Vec<TestId<'a>>,
+    /// Tests that failed before and pass now.
+    pub newly_passing: Vec<TestId<'a>>,
+    pub added: Vec<TestId<'a>>,
+    pub removed: Vec<TestId<'a>>,
+    /// Tests that ran before and are ignored now.
+    pub newly_ignored: Vec<TestId<'a>>,
+    /// Tests that got noticeably slower, the biggest slowdown first.
+    pub slower: Vec<DurationChange<'a>>,
+}
+
+impl<'a> RunDiff<'a> {
+    pub fn is_empty(&self) -> bool {
+        self.newly_failing.is_empty() && self.newly_passing.is_empty() && self.added.is_empty() &&
+            self.removed.is_empty() && self.newly_ignored.is_empty() && self.slower.is_empty()
+    }
+}
+
+// Cargo names test binaries like `foo-5a7be5d1b9c8e0f6`, with a hash that
+// changes from build to build, so suites are matched up without it.
+fn suite_key(name: &str) -> String {
+    let mut key = String::with_capacity(name.len());
+    let mut rest = name;
+
+    while let Some(i) = rest.find('-') {
+        key.push_str(&rest[..i]);
+
+        let after = &rest[i + 1..];
+        let bytes = after.as_bytes();
+        let is_hash = bytes.len() >= 16 && bytes[..16].iter().all(u8::is_ascii_hexdigit) &&
+            !matches!(bytes.get(16), Some(x) if x.is_ascii_alphanumeric());
+
+        if is_hash {
+            rest = &after[16..];
+        } else {
+            key.push('-');
+            rest = after;
+        }
+    }
+
+    key.push_str(rest);
+    key
+}
+
+// The key of each suite in `run`, counting how many suites before it had the
+// same one. Without a target in its name, the lib and bin unit tests of a
+// crate look alike, so they're told apart by the order they ran in.
+fn suite_keys(run: &TestRun) -> Vec<(String, usize)> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    run.suites
+        .iter()
+        .map(|x| {
+            let key = suite_key(x.name);
+            let n = seen.entry(key.clone()).or_insert(0);
+            *n += 1;
+            (key, *n - 1)
+        })
+        .collect()
+}
+
+fn is_failing(t: &Test) -> bool {
+    t.status == "fail" || t.status == "incomplete"
+}
+
+/// Lists the tests whose outcome changed between `old` and `new`, the tests
+/// that were added or removed, and the tests that got slower.
+pub fn diff<'a>(old: &TestRun<'a>, new: &TestRun<'a>) -> RunDiff<'a> {
+    let old_keys = suite_keys(old);
+    let mut old_tests: HashMap<(&(String, usize), &str), &Test> = HashMap::new();
+
+    for (suite, key) in old.suites.iter().zip(&old_keys) {
+        for t in &suite.tests {
+            old_tests.insert((key, t.name), t);
+        }
+    }
+
+    let mut diff = RunDiff {
+        newly_failing: vec![],
+        newly_passing: vec![],
+        added: vec![],
+        removed: vec![],
+        newly_ignored: vec![],
+        slower: vec![],
+    };
+
+    let new_keys = suite_keys(new);
+
+    for (suite, key) in new.suites.iter().zip(&new_keys) {
+        for t in &suite.tests {
+            let id = || TestId {
+                suite: suite.name,
+                name: t.name,
+            };
+
+            let before = match old_tests.remove(&(key, t.name)) {
+                Some(x) => x,
+                None => {
+                    if is_failing(t) {
+                        diff.newly_failing.push(id());
+                    }
+                    diff.added.push(id());
+                    continue;
+                }
+            };
+
+            if is_failing(t) && !is_failing(before) {
+                diff.newly_failing.push(id());
+            } else if t.status == "pass" && is_failing(before) {
+                diff.newly_passing.push(id());
+            } else if t.status == "ignored" && before.status != "ignored" {
+                diff.newly_ignored.push(id());
+            }
+
+            if let (Some(old), Some(new)) = (before.duration, t.duration) {
+                if new >= old * SLOWDOWN_RATIO && new - old >= SLOWDOWN_SECONDS {
+                    diff.slower.push(DurationChange { test: id(), old, new });
+                }
+            }
+        }
+    }
+
+    // What's left didn't run this time, listed in the order it ran before.
+    for (suite, key) in old.suites.iter().zip(&old_keys) {
+        for t in &suite.tests {
+            if old_tests.contains_key(&(key, t.name)) {
+                diff.removed.push(TestId {
+                    suite: suite.name,
+                    name: t.name,
+                });
+            }
+        }
+    }
+
+    diff.slower.sort_by(|a, b| {
+        (b.new - b.old)
+            .partial_cmp(&(a.new - a.old))
+            .unwrap_or(::std::cmp::Ordering::Equal)
+    });
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, suite_key, TestId};
+    use super::super::{cargo_test_result_parser, nextest_result_parser, parsed};
+
+    #[test]
+    fn it_should_match_suites_across_builds() {
+        assert_eq!(
+            suite_key("unittests src/lib.rs (target/debug/deps/foo-5a7be5d1b9c8e0f6)"),
+            suite_key("unittests src/lib.rs (target/debug/deps/foo-0c3c5a3b0c7e1a2b)")
+        );
+        assert_eq!(suite_key("target/debug/deps/foo_bar-5a7be5d1b9c8e0f6.exe"), "target/debug/deps/foo_bar.exe");
+        assert_eq!(suite_key("my-crate"), "my-crate");
+    }
+
+    #[test]
+    fn it_should_diff_two_runs() {
+        let old = parsed(nextest_result_parser(b"    Starting 6 tests across 1 binaries
+        PASS [   0.004s] demo tests::steady
+        PASS [   0.010s] demo tests::breaks
+        FAIL [   0.005s] demo tests::fixed
+        PASS [   0.200s] demo tests::slows_down
+        PASS [   0.003s] demo tests::goes_away
+        PASS [   0.003s] demo tests::gets_ignored
+------------
+     Summary [   0.225s] 6 tests run: 5 passed, 1 failed, 0 skipped
+"));
+
+        let new = parsed(nextest_result_parser(b"    Starting 7 tests across 1 binaries
+        PASS [   0.009s] demo tests::steady
+        FAIL [   0.011s] demo tests::breaks
+        PASS [   0.004s] demo tests::fixed
+        PASS [   0.900s] demo tests::slows_down
+        SKIP [         ] demo tests::gets_ignored
+        PASS [   0.002s] demo tests::is_new
+        FAIL [   0.002s] demo tests::is_new_and_broken
+------------
+     Summary [   0.928s] 6 tests run: 4 passed, 2 failed, 1 skipped
+"));
+
+        let id = |name| TestId {
+            suite: "demo",
+            name,
+        };
+
+        let changes = diff(&old, &new);
+
+        assert_eq!(changes.newly_failing, vec![id("tests::breaks"), id("tests::is_new_and_broken")]);
+        assert_eq!(changes.newly_passing, vec![id("tests::fixed")]);
+        assert_eq!(changes.added, vec![id("tests::is_new"), id("tests::is_new_and_broken")]);
+        assert_eq!(changes.removed, vec![id("tests::goes_away")]);
+        assert_eq!(changes.newly_ignored, vec![id("tests::gets_ignored")]);
+
+        let slower: Vec<&str> = changes.slower.iter().map(|x| x.test.name).collect();
+        assert_eq!(slower, vec!["tests::slows_down"]);
+
+        assert!(diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn it_should_tell_apart_suites_with_the_same_key() {
+        // The lib and bin unit tests of a crate, before cargo named targets.
+        let run = |lib: &str, bin: &str| {
+            format!(
+                "     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 1 test
+test tests::it_works ... {}
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+     Running target/debug/deps/foo-0c3c5a3b0c7e1a2b
+
+running 1 test
+test tests::it_works ... {}
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+",
+                lib,
+                bin
+            )
+        };
+
+        let old = run("ok", "FAILED");
+        let new = run("ok", "ok");
+
+        let old = parsed(cargo_test_result_parser(old.as_bytes()));
+        let new = parsed(cargo_test_result_parser(new.as_bytes()));
+
+        let changes = diff(&old, &new);
+
+        assert!(changes.added.is_empty());
+        assert!(changes.removed.is_empty());
+        assert_eq!(
+            changes.newly_passing,
+            vec![TestId {
+                suite: "target/debug/deps/foo-0c3c5a3b0c7e1a2b",
+                name: "tests::it_works",
+            }]
+        );
+    }
+}
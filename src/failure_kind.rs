@@ -0,0 +1,261 @@
+/// Why a test failed, as far as its output tells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FailureKind<'a> {
+    /// The test panicked.
+    Panic,
+    /// A `#[should_panic]` test returned without panicking.
+    DidNotPanic,
+    /// A `#[should_panic(expected = "...")]` test panicked, but the message
+    /// did not contain the expected string. The actual message is missing
+    /// when the panic payload wasn't a string.
+    WrongPanic {
+        expected: &'a str,
+        actual: Option<&'a str>,
+    },
+    /// The test returned an `Err`, printed with its `Debug` impl.
+    ReturnedError(&'a str),
+    /// The test's process was killed by a signal, like `SIGSEGV`. Only
+    /// nextest runs each test in a process of its own.
+    Signal(&'a str),
+    /// The test ran past nextest's `slow-timeout` and was killed.
+    Timeout,
+}
+
+// Values print as `"foo"` since 1.53, and as `` `"foo"` `` before.
+fn unquote(x: &str) -> &str {
+    let x = x.trim().trim_end_matches(',');
+    let x = x.trim_start_matches('`').trim_end_matches('`');
+
+    if x.len() >= 2 && (x.starts_with('"') && x.ends_with('"') || x.starts_with('\'') && x.ends_with('\'')) {
+        &x[1..x.len() - 1]
+    } else {
+        x
+    }
+}
+
+fn field<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    text.lines()
+        .map(str::trim)
+        .find(|x| x.starts_with(name))
+        .map(|x| unquote(&x[name.len()..]))
+}
+
+// The message of an old style `panicked at 'message', file:line` panic.
+fn quoted_panic_message(text: &str) -> Option<&str> {
+    let start = text.find("panicked at '")? + "panicked at '".len();
+    let end = text[start..].rfind("', ")?;
+
+    Some(&text[start..start + end])
+}
+
+// Older test harnesses followed the error with a panic about the
+// termination value.
+fn is_termination_panic(text: &str) -> bool {
+    text.starts_with("thread '") && text.contains("the test returned a termination value")
+}
+
+/// Splits what a test printed from the `Error: ...` it returned, if it
+/// returned one rather than panicking. The error has to end the output, or
+/// be followed by the panic or note libtest printed about it; anything else
+/// means the test only printed it.
+pub fn split_returned_error(text: &str) -> Option<(&str, &str)> {
+    let start = if text.starts_with("Error: ") {
+        0
+    } else {
+        text.rfind("\nError: ")? + 1
+    };
+
+    let rest = &text[start..];
+    let mut end = rest.len();
+    let mut offset = 0;
+
+    for line in rest.split('\n') {
+        if line.starts_with("note:") {
+            if !line.contains("RUST_BACKTRACE") {
+                return None;
+            }
+
+            end = offset;
+            break;
+        }
+
+        if line.starts_with("thread '") {
+            if !is_termination_panic(&rest[offset..]) {
+                return None;
+            }
+
+            end = offset;
+            break;
+        }
+
+        offset += line.len() + 1;
+    }
+
+    Some((text[..start].trim_end(), rest[..end].trim_end()))
+}
+
+/// Works out why a test failed from the output block under its name.
+pub fn failure_kind(text: &str) -> FailureKind<'_> {
+    if text.contains("note: test did not panic as expected") {
+        return FailureKind::DidNotPanic;
+    }
+
+    // Before 1.47, all on one line.
+    if let Some(i) = text.find("note: panic did not include expected string ") {
+        let expected = text[i + "note: panic did not include expected string ".len()..]
+            .lines()
+            .next()
+            .unwrap_or("");
+
+        return FailureKind::WrongPanic {
+            expected: unquote(expected),
+            actual: quoted_panic_message(text),
+        };
+    }
+
+    if let Some(i) = text.find("note: panic did not contain expected string") {
+        let rest = &text[i..];
+
+        if let Some(expected) = field(rest, "expected substring:") {
+            return FailureKind::WrongPanic {
+                expected,
+                actual: field(rest, "panic message:"),
+            };
+        }
+    }
+
+    if let Some(i) = text.find("note: expected panic with string value") {
+        if let Some(expected) = field(&text[i..], "expected substring:") {
+            return FailureKind::WrongPanic {
+                expected,
+                actual: None,
+            };
+        }
+    }
+
+    if let Some((_, error)) = split_returned_error(text) {
+        return FailureKind::ReturnedError(error["Error: ".len()..].trim_start());
+    }
+
+    FailureKind::Panic
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{failure_kind, split_returned_error, FailureKind};
+
+    #[test]
+    fn it_should_find_tests_that_did_not_panic() {
+        assert_eq!(
+            failure_kind("note: test did not panic as expected at src/lib.rs:15:8"),
+            FailureKind::DidNotPanic
+        );
+        assert_eq!(failure_kind("note: test did not panic as expected"), FailureKind::DidNotPanic);
+    }
+
+    #[test]
+    fn it_should_find_panics_with_the_wrong_message() {
+        assert_eq!(
+            failure_kind(
+                "thread 'wrong_panic' panicked at src/lib.rs:19:24:
+foo
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+note: panic did not contain expected string
+      panic message: \"foo\"
+ expected substring: \"bar\""
+            ),
+            FailureKind::WrongPanic {
+                expected: "bar",
+                actual: Some("foo"),
+            }
+        );
+
+        assert_eq!(
+            failure_kind(
+                "thread 'wrong_panic' panicked at 'foo', src/lib.rs:19:24
+note: panic did not contain expected string
+      panic message: `\"foo\"`,
+ expected substring: `\"bar\"`"
+            ),
+            FailureKind::WrongPanic {
+                expected: "bar",
+                actual: Some("foo"),
+            }
+        );
+
+        assert_eq!(
+            failure_kind(
+                "thread 'wrong_panic' panicked at 'foo', src/lib.rs:19
+note: Run with `RUST_BACKTRACE=1` for a backtrace.
+note: panic did not include expected string 'bar'"
+            ),
+            FailureKind::WrongPanic {
+                expected: "bar",
+                actual: Some("foo"),
+            }
+        );
+    }
+
+    #[test]
+    fn it_should_find_returned_errors() {
+        assert_eq!(failure_kind("Error: \"nope\""), FailureKind::ReturnedError("\"nope\""));
+
+        assert_eq!(
+            split_returned_error(
+                "connecting
+Error: Os { code: 111, kind: ConnectionRefused, message: \"Connection refused\" }
+thread 'tests::connects' panicked at 'assertion failed: `(left == right)`
+  left: `1`,
+ right: `0`: the test returned a termination value with a non-zero status code (1) which indicates a failure', src/libtest/lib.rs:335:5
+note: Run with `RUST_BACKTRACE=1` for a backtrace."
+            ),
+            Some((
+                "connecting",
+                "Error: Os { code: 111, kind: ConnectionRefused, message: \"Connection refused\" }"
+            ))
+        );
+
+        assert_eq!(
+            failure_kind(
+                "Error: failed to read config
+
+Caused by:
+    No such file or directory (os error 2)"
+            ),
+            FailureKind::ReturnedError(
+                "failed to read config
+
+Caused by:
+    No such file or directory (os error 2)"
+            )
+        );
+    }
+
+    #[test]
+    fn it_should_not_take_printed_errors_for_returned_ones() {
+        assert_eq!(
+            failure_kind("Error: retrying\nthread 'fails' panicked at src/lib.rs:6:48:\nboom"),
+            FailureKind::Panic
+        );
+        assert_eq!(
+            failure_kind("Error: retrying\nnote: test did not panic as expected at src/lib.rs:15:8"),
+            FailureKind::DidNotPanic
+        );
+        assert_eq!(
+            split_returned_error("Error: retrying\nnote: test did not panic as expected at src/lib.rs:15:8"),
+            None
+        );
+        assert_eq!(
+            split_returned_error("Error: \"nope\"\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace"),
+            Some(("", "Error: \"nope\""))
+        );
+    }
+
+    #[test]
+    fn it_should_default_to_a_panic() {
+        assert_eq!(
+            failure_kind("thread 'fails' panicked at src/lib.rs:6:48:\nboom"),
+            FailureKind::Panic
+        );
+    }
+}
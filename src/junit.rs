@@ -0,0 +1,532 @@
+//! Reads JUnit XML reports back into the `Suite` model, whether they came
+//! from `report::to_junit`, nextest or another tool.
+
+use super::assertion::parse_assertion;
+use super::backtrace::split_backtrace;
+use super::failure_kind::failure_kind;
+use super::{count_status, panic_location, SourceLocation, Suite, Test};
+
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|x| x.0 == name)
+            .map(|x| x.1.as_str())
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |x| x.name == name)
+    }
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        let end = match rest.find(';') {
+            Some(x) => x,
+            None => break,
+        };
+
+        let decoded = match &rest[1..end] {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            x if x.starts_with("#x") => u32::from_str_radix(&x[2..], 16).ok().and_then(std::char::from_u32),
+            x if x.starts_with('#') => x[1..].parse().ok().and_then(std::char::from_u32),
+            _ => None,
+        };
+
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+struct Reader<'a> {
+    xml: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.xml[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at byte {}", message, self.pos)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    // Moves past `end`, returning what came before it.
+    fn skip_past(&mut self, end: &str) -> Result<&'a str, String> {
+        match self.rest().find(end) {
+            Some(i) => {
+                let skipped = &self.rest()[..i];
+                self.pos += i + end.len();
+                Ok(skipped)
+            }
+            None => Err(self.error(&format!("expected `{}`", end))),
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str, String> {
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>' || c == '=')
+            .unwrap_or(rest.len());
+
+        if end == 0 {
+            return Err(self.error("expected a name"));
+        }
+
+        self.pos += end;
+        Ok(&rest[..end])
+    }
+
+    // Declarations, comments and processing instructions carry nothing we need.
+    fn skip_misc(&mut self) -> Result<bool, String> {
+        let end = if self.rest().starts_with("<?") {
+            "?>"
+        } else if self.rest().starts_with("<!--") {
+            "-->"
+        } else if self.rest().starts_with("<!") && !self.rest().starts_with("<![CDATA[") {
+            ">"
+        } else {
+            return Ok(false);
+        };
+
+        self.skip_past(end).map(|_| true)
+    }
+
+    fn element(&mut self) -> Result<Element, String> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expected `<`"));
+        }
+
+        self.pos += 1;
+
+        let mut element = Element {
+            name: self.name()?.to_string(),
+            attributes: vec![],
+            children: vec![],
+            text: String::new(),
+        };
+
+        loop {
+            self.skip_whitespace();
+
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(element);
+            }
+
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+
+            let name = self.name()?.to_string();
+            self.skip_whitespace();
+
+            if !self.rest().starts_with('=') {
+                return Err(self.error("expected `=`"));
+            }
+
+            self.pos += 1;
+            self.skip_whitespace();
+
+            let quote = match self.rest().chars().next() {
+                Some(x) if x == '"' || x == '\'' => x,
+                _ => return Err(self.error("expected a quoted value")),
+            };
+
+            self.pos += 1;
+            let value = self.skip_past(if quote == '"' { "\"" } else { "'" })?;
+            element.attributes.push((name, unescape(value)));
+        }
+
+        loop {
+            if self.rest().is_empty() {
+                return Err(self.error(&format!("`<{}>` is never closed", element.name)));
+            }
+
+            if self.rest().starts_with("</") {
+                let start = self.pos;
+                self.pos += 2;
+
+                if self.name()? != element.name {
+                    self.pos = start;
+                    return Err(self.error(&format!("expected `</{}>`", element.name)));
+                }
+
+                self.skip_whitespace();
+                self.skip_past(">")?;
+                return Ok(element);
+            }
+
+            if self.rest().starts_with("<![CDATA[") {
+                self.pos += "<![CDATA[".len();
+                element.text.push_str(self.skip_past("]]>")?);
+            } else if self.skip_misc()? {
+                continue;
+            } else if self.rest().starts_with('<') {
+                element.children.push(self.element()?);
+            } else {
+                let end = self.rest().find('<').unwrap_or(self.rest().len());
+                element.text.push_str(&unescape(&self.rest()[..end]));
+                self.pos += end;
+            }
+        }
+    }
+
+    fn document(&mut self) -> Result<Element, String> {
+        loop {
+            self.skip_whitespace();
+
+            if !self.skip_misc()? {
+                break;
+            }
+        }
+
+        self.element()
+    }
+}
+
+struct JunitTest {
+    name: String,
+    status: &'static str,
+    error: Option<String>,
+    stdout: Option<String>,
+    file: Option<String>,
+    line: Option<i64>,
+    time: Option<f64>,
+    retries: i64,
+}
+
+struct JunitSuite {
+    name: String,
+    /// The `tests` attribute, which should match the test cases listed.
+    declared: Option<i64>,
+    tests: Vec<JunitTest>,
+}
+
+fn non_empty(x: &str) -> Option<String> {
+    let x = x.trim();
+
+    if x.is_empty() { None } else { Some(x.to_string()) }
+}
+
+fn read_test(e: &Element) -> JunitTest {
+    let failure = e.children("failure").chain(e.children("error")).next();
+
+    let status = match failure {
+        // How `report::to_junit` writes tests that never finished.
+        Some(x) if x.name == "error" && x.attribute("message") == Some("test did not finish") => {
+            "incomplete"
+        }
+        Some(_) => "fail",
+        None if e.children("skipped").next().is_some() => "ignored",
+        None => "pass",
+    };
+
+    // nextest lists the failed attempts of a retried test.
+    let retries = ["flakyFailure", "flakyError", "rerunFailure", "rerunError"]
+        .iter()
+        .map(|x| e.children(x).count() as i64)
+        .sum();
+
+    JunitTest {
+        name: e.attribute("name").unwrap_or("").to_string(),
+        status,
+        error: failure.and_then(|x| non_empty(&x.text).or_else(|| x.attribute("message").and_then(non_empty))),
+        stdout: e.children("system-out").next().and_then(|x| non_empty(&x.text)),
+        file: e.attribute("file").map(str::to_string),
+        line: e.attribute("line").and_then(|x| x.parse().ok()),
+        time: e.attribute("time").and_then(|x| x.parse().ok()),
+        retries,
+    }
+}
+
+fn read_suites(e: &Element, suites: &mut Vec<JunitSuite>) {
+    if e.name == "testsuite" {
+        suites.push(JunitSuite {
+            name: e.attribute("name").unwrap_or("").to_string(),
+            declared: e.attribute("tests").and_then(|x| x.parse().ok()),
+            tests: e.children("testcase").map(read_test).collect(),
+        });
+    }
+
+    for x in e.children.iter().filter(|x| x.name == "testsuite" || x.name == "testsuites") {
+        read_suites(x, suites);
+    }
+}
+
+/// A JUnit XML report, holding the unescaped text its suites borrow.
+pub struct JunitReport {
+    suites: Vec<JunitSuite>,
+}
+
+impl JunitReport {
+    pub fn parse(xml: &str) -> Result<JunitReport, String> {
+        let root = Reader { xml, pos: 0 }.document()?;
+        let mut suites = vec![];
+
+        read_suites(&root, &mut suites);
+
+        Ok(JunitReport { suites })
+    }
+
+    pub fn suites(&self) -> Vec<Suite<'_, '_, '_, '_, '_>> {
+        self.suites.iter().map(to_suite).collect()
+    }
+}
+
+fn to_test(x: &JunitTest) -> Test<'_, '_, '_> {
+    let (error, backtrace) = match x.error {
+        Some(ref x) => {
+            let (error, backtrace) = split_backtrace(x);
+            (Some(error), backtrace)
+        }
+        None => (None, vec![]),
+    };
+
+    let location = match (&x.file, x.line) {
+        (Some(file), Some(line)) => Some(SourceLocation {
+            file: file.as_str().into(),
+            line,
+            column: None,
+        }),
+        _ => error.and_then(panic_location),
+    };
+
+    Test {
+        name: &x.name,
+        status: x.status,
+        error,
+        slow_warning: false,
+        stdout: x.stdout.as_deref(),
+        location,
+        assertion: error.and_then(parse_assertion),
+        backtrace,
+        failure_kind: if x.status == "fail" { x.error.as_ref().map(|x| failure_kind(x)) } else { None },
+        duration: x.time,
+        retries: x.retries,
+    }
+}
+
+fn to_suite(x: &JunitSuite) -> Suite<'_, '_, '_, '_, '_> {
+    let tests: Vec<Test> = x.tests.iter().map(to_test).collect();
+
+    let passed = count_status(&tests, "pass");
+    let failed = count_status(&tests, "fail");
+    let ignored = count_status(&tests, "ignored");
+    let incomplete = count_status(&tests, "incomplete");
+
+    // Like `running N tests`, `tests` can count tests that never reported.
+    let planned = x.declared.unwrap_or(tests.len() as i64);
+    let missing = (planned - tests.len() as i64).max(0);
+
+    let warnings = match x.declared {
+        Some(n) if n != tests.len() as i64 => vec![format!(
+            "`tests=\"{}\"` but {} test cases were listed",
+            n,
+            tests.len()
+        )],
+        _ => vec![],
+    };
+
+    Suite {
+        name: &x.name,
+        state: if failed + incomplete + missing > 0 { "fail" } else { "pass" },
+        passed,
+        failed,
+        ignored,
+        measured: count_status(&tests, "measured"),
+        total: tests.len() as i64,
+        planned,
+        missing,
+        slow: vec![],
+        failure_names: vec![],
+        rerun_args: vec![],
+        warnings,
+        tests,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::report::to_junit;
+    use super::super::{cargo_test_result_parser, parsed, FailureKind, SourceLocation};
+    use super::JunitReport;
+
+    #[test]
+    fn it_should_read_a_junit_report() {
+        let report = JunitReport::parse(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!-- written by nextest -->
+<testsuites name=\"nextest-run\" tests=\"4\" failures=\"1\" errors=\"0\">
+  <testsuite name=\"demo\" tests=\"4\" failures=\"1\" errors=\"0\" skipped=\"1\">
+    <testcase name=\"tests::passes\" classname=\"demo\" time=\"0.004\"/>
+    <testcase name=\"tests::fails\" classname=\"demo\" time=\"0.005\">
+      <failure message=\"thread &apos;tests::fails&apos; panicked\" type=\"test failure\"><![CDATA[thread 'tests::fails' panicked at src/lib.rs:6:48:
+assertion `left == right` failed
+  left: 1
+ right: 2]]></failure>
+      <system-out>hello &lt;world&gt; &#x2713;</system-out>
+    </testcase>
+    <testcase name=\"tests::flaky\" classname=\"demo\" time=\"0.002\">
+      <flakyFailure message=\"not yet\" type=\"test failure\"/>
+    </testcase>
+    <testcase name='tests::ignored' classname='demo'>
+      <skipped/>
+    </testcase>
+  </testsuite>
+</testsuites>
+",
+        ).unwrap();
+
+        let suites = report.suites();
+        assert_eq!(suites.len(), 1);
+
+        let suite = &suites[0];
+        assert_eq!(suite.name, "demo");
+        assert_eq!(suite.state, "fail");
+        assert_eq!((suite.passed, suite.failed, suite.ignored, suite.total), (2, 1, 1, 4));
+        assert!(suite.warnings.is_empty());
+
+        let fails = &suite.tests[1];
+        assert_eq!(fails.status, "fail");
+        assert_eq!(fails.duration, Some(0.005));
+        assert_eq!(fails.stdout, Some("hello <world> \u{2713}"));
+        assert_eq!(fails.failure_kind, Some(FailureKind::Panic));
+        assert_eq!(
+            fails.location,
+            Some(SourceLocation {
+                file: "src/lib.rs".into(),
+                line: 6,
+                column: Some(48),
+            })
+        );
+        assert_eq!(fails.assertion.as_ref().map(|x| x.left), Some("1"));
+
+        assert_eq!((suite.tests[2].status, suite.tests[2].retries), ("pass", 1));
+        assert_eq!(suite.tests[3].status, "ignored");
+    }
+
+    #[test]
+    fn it_should_read_back_its_own_reports() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 3 tests
+test tests::it_passes ... ok
+test tests::it_fails ... FAILED
+test tests::it_is_ignored ... ignored
+
+failures:
+
+---- tests::it_fails stdout ----
+thread 'tests::it_fails' panicked at 'a < b & c', src/lib.rs:10
+note: Run with `RUST_BACKTRACE=1` for a backtrace.
+
+
+failures:
+    tests::it_fails
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+
+";
+
+        let run = parsed(cargo_test_result_parser(output));
+
+        let report = JunitReport::parse(&to_junit(&run)).unwrap();
+        let suites = report.suites();
+
+        for (read, parsed) in suites.iter().zip(&run.suites) {
+            assert_eq!(read.name, parsed.name);
+            assert_eq!(
+                (read.state, read.passed, read.failed, read.ignored, read.total),
+                (parsed.state, parsed.passed, parsed.failed, parsed.ignored, parsed.total)
+            );
+
+            for (a, b) in read.tests.iter().zip(&parsed.tests) {
+                assert_eq!((a.name, a.status, a.error), (b.name, b.status, b.error));
+                assert_eq!(a.location, b.location);
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_keep_the_counts_of_a_crashed_suite() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 3 tests
+test tests::it_passes ... ok
+test tests::it_segfaults ... 
+error: test failed, to rerun pass '--lib'
+
+Caused by:
+  process didn't exit successfully: `/foo/target/debug/deps/foo-5a7be5d1b9c8e0f6` (signal: 11, SIGSEGV: invalid memory reference)
+";
+
+        let run = parsed(cargo_test_result_parser(output));
+
+        let report = JunitReport::parse(&to_junit(&run)).unwrap();
+        let read = &report.suites()[0];
+        let parsed = &run.suites[0];
+
+        assert_eq!((parsed.total, parsed.planned, parsed.missing), (2, 3, 1));
+        assert_eq!(
+            (read.state, read.passed, read.total, read.planned, read.missing),
+            (parsed.state, parsed.passed, parsed.total, parsed.planned, parsed.missing)
+        );
+    }
+
+    #[test]
+    fn it_should_warn_about_miscounted_suites() {
+        let report = JunitReport::parse(
+            "<testsuite name=\"foo\" tests=\"2\"><testcase name=\"a\"/></testsuite>",
+        ).unwrap();
+
+        assert_eq!(
+            report.suites()[0].warnings,
+            vec!["`tests=\"2\"` but 1 test cases were listed"]
+        );
+        assert_eq!((report.suites()[0].planned, report.suites()[0].missing), (2, 1));
+    }
+
+    #[test]
+    fn it_should_reject_broken_xml() {
+        assert_eq!(
+            JunitReport::parse("<testsuites><testsuite name=\"foo\"></testsuites>").err(),
+            Some("expected `</testsuite>` at byte 34".to_string())
+        );
+    }
+}
@@ -1,11 +1,42 @@
 #[macro_use]
 extern crate nom;
+#[macro_use]
+extern crate serde_json;
+
 use std::str;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+
+use nom::{line_ending, digit, space, IResult};
+
+use assertion::parse_assertion;
+use backtrace::split_backtrace;
+use failure_kind::{failure_kind, split_returned_error};
 
-use nom::{line_ending, digit, space};
+mod assertion;
+mod backtrace;
+mod diff;
+mod failure_kind;
+mod junit;
+mod merge;
+mod nextest;
+mod normalize;
+pub mod report;
+mod stream;
+
+pub use assertion::Assertion;
+pub use backtrace::Frame;
+pub use diff::{diff, DurationChange, RunDiff, TestId};
+pub use failure_kind::FailureKind;
+pub use junit::JunitReport;
+pub use merge::merge;
+pub use nextest::nextest_result_parser;
+pub use normalize::{normalize, strip_ansi, strip_carriage_returns};
+pub use stream::{interleave, Event, Interleaver, StreamParser, SuiteBlock, SuiteReader};
 
 named!(
-    rest_of_line<&str>,
+    rest_of_line<&'a str>,
     do_parse!(
         content: map_res!(
             nom::not_line_ending,
@@ -16,6 +47,18 @@ named!(
     )
 );
 
+named!(
+    any_line<&'a str>,
+    do_parse!(
+        line: map_res!(
+            nom::not_line_ending,
+            str::from_utf8
+        ) >>
+        opt!(complete!(line_ending)) >>
+        (line)
+    )
+);
+
 named!(
     compiling<()>,
     do_parse!(
@@ -62,7 +105,7 @@ named!(
 );
 
 named!(
-    suite_line<&str>,
+    suite_line<&'a str>,
     do_parse!(
         ws!(
             alt!(tag!("Running") | tag!("Doc-tests"))
@@ -73,61 +116,343 @@ named!(
 );
 
 named!(
-    suite_count<()>,
+    suite_count<i64>,
     do_parse!(
         ws!(tag!("running")) >>
+        count: digits >>
         rest_of_line >>
-        ()
+        (count)
     )
 );
 
 named!(
-    ok<&str>,
+    ok<&'a str>,
     map!(tag!("ok"),
     |_| "pass")
 );
 
 named!(
-    failed<&str>,
+    failed<&'a str>,
     map!(tag!("FAILED"),
     |_| "fail")
 );
 
 named!(
-    ok_or_failed<&str>,
+    ok_or_failed<&'a str>,
     alt!(ok | failed)
 );
 
+named!(
+    ignored<&'a str>,
+    do_parse!(
+        tag!("ignored") >>
+        opt!(
+            complete!(
+                preceded!(char!(','), nom::not_line_ending)
+            )
+        ) >>
+        ("ignored")
+    )
+);
+
+// `cargo bench` reports a timing where a test reports its verdict.
+named!(
+    bench<&'a str>,
+    do_parse!(
+        opt!(complete!(space)) >>
+        tag!("bench:") >>
+        call!(nom::not_line_ending) >>
+        ("measured")
+    )
+);
+
+// A test that has no verdict yet, because the suite crashed or was killed,
+// or because its verdict is printed later. Its line ends there, unless a slow
+// warning or cargo's report of the crash was printed onto it.
+named!(
+    incomplete<&'a str>,
+    do_parse!(
+        opt!(complete!(space)) >>
+        alt_complete!(
+            peek!(line_ending) |
+            eof!() |
+            peek!(tag!("test ")) |
+            peek!(tag!("error: "))
+        ) >>
+        ("incomplete")
+    )
+);
+
 #[derive(Debug, PartialEq)]
 pub struct Test<'a, 'b, 'c> {
     pub name: &'a str,
     pub status: &'b str,
     pub error: Option<&'c str>,
+    pub slow_warning: bool,
+    /// What the test printed, when run with `--show-output` or `--nocapture`.
+    pub stdout: Option<&'c str>,
+    /// Where a failed test panicked.
+    pub location: Option<SourceLocation<'c>>,
+    /// The two sides of the `assert_eq!` or `assert_ne!` a failed test
+    /// panicked in.
+    pub assertion: Option<Assertion<'c>>,
+    /// The frames of the `stack backtrace:` printed after the panic, if any.
+    pub backtrace: Vec<Frame<'c>>,
+    /// Why a failed test failed, when its output was captured.
+    pub failure_kind: Option<FailureKind<'c>>,
+    /// How long the test took in seconds, when the runner reports it.
+    pub duration: Option<f64>,
+    /// How many times the test was retried before its final result.
+    pub retries: i64,
+}
+
+// `#[should_panic]` tests are listed as `test name - should panic ... ok`.
+fn strip_should_panic(name: &str) -> &str {
+    name.strip_suffix(" - should panic").unwrap_or(name)
 }
 
 named!(
-    test_result<Test>,
+    test_result<Test<'a, 'a, 'a>>,
     do_parse!(
         tag!("test") >>
         space >>
-        name: map_res!(
-            take_until_s!(" ..."),
-            str::from_utf8
+        name: map!(
+            verify!(
+                map_res!(
+                    take_until_s!(" ..."),
+                    str::from_utf8
+                ),
+                |name: &str| !name.contains('\n')
+            ),
+            strip_should_panic
         ) >>
         tag!(" ...") >>
-        status: ws!(ok_or_failed) >>
+        status: alt_complete!(
+            ws!(alt!(ok_or_failed | ignored)) |
+            bench |
+            incomplete
+        ) >>
+        (Test {
+            name,
+            status,
+            error: None,
+            slow_warning: false,
+            stdout: None,
+            location: None,
+            assertion: None,
+            backtrace: vec![],
+            failure_kind: None,
+            duration: None,
+            retries: 0
+        })
+    )
+);
+
+named!(
+    slow_warning<Test<'a, 'a, 'a>>,
+    do_parse!(
+        name: flat_map!(
+            call!(nom::not_line_ending),
+            complete!(
+                do_parse!(
+                    tag!("test") >>
+                    space >>
+                    name: verify!(
+                        map_res!(
+                            take_until_s!(" has been running for over "),
+                            str::from_utf8
+                        ),
+                        |name: &str| !name.contains(" ...")
+                    ) >>
+                    tag!(" has been running for over ") >>
+                    digits >>
+                    tag!("seconds") >>
+                    (name)
+                )
+            )
+        ) >>
+        opt!(complete!(line_ending)) >>
+        status: alt_complete!(
+            ws!(alt!(ok_or_failed | ignored)) |
+            bench |
+            incomplete
+        ) >>
         (Test {
-            name: name,
-            status: status,
-            error: None
+            name,
+            status,
+            error: None,
+            slow_warning: true,
+            stdout: None,
+            location: None,
+            assertion: None,
+            backtrace: vec![],
+            failure_kind: None,
+            duration: None,
+            retries: 0
+        })
+    )
+);
+
+// With `--nocapture` and one test thread, whatever a test prints lands
+// between its name and its verdict, which then gets a line of its own. A
+// line like `Running migrations` is output too; the test list only ends at
+// `test result:`, or at cargo's `error: test failed` when the binary crashed.
+fn captured_output(input: &[u8]) -> IResult<&[u8], (&str, &str)> {
+    let mut start = match input.iter().position(|&c| c == b'\n') {
+        Some(i) => i + 1,
+        None => return IResult::Error(error_position!(nom::ErrorKind::Custom(0), input)),
+    };
+
+    while start < input.len() {
+        let end = input[start..]
+            .iter()
+            .position(|&c| c == b'\n')
+            .map_or(input.len(), |i| start + i + 1);
+
+        let line = match str::from_utf8(&input[start..end]) {
+            Ok(x) => x,
+            Err(_) => break,
+        };
+
+        let status = match line.trim() {
+            "ok" => "pass",
+            "FAILED" => "fail",
+            _ => {
+                let is_test = line.starts_with("test ") &&
+                    (line.contains(" ... ") || line.contains(" has been running for over "));
+
+                if is_test || line.trim_start().starts_with("test result: ") ||
+                    line.trim_start().starts_with("error: test failed")
+                {
+                    break;
+                }
+
+                start = end;
+                continue;
+            }
+        };
+
+        let rest = input[end..]
+            .iter()
+            .position(|c| !(*c as char).is_whitespace())
+            .map_or(input.len(), |i| end + i);
+
+        return match str::from_utf8(&input[..start]) {
+            Ok(x) => IResult::Done(&input[rest..], (x.trim_start_matches('\n').trim_end(), status)),
+            Err(_) => break,
+        };
+    }
+
+    IResult::Error(error_position!(nom::ErrorKind::Custom(0), input))
+}
+
+// Splits what a failed test printed from the panic message, the returned
+// `Error: ...`, or the note about a `#[should_panic]` test that didn't panic,
+// that follows it. Without any of them, it's all taken as the error.
+fn split_panic_message<'a>(name: &str, output: &'a str) -> (Option<&'a str>, &'a str) {
+    if let Some((stdout, error)) = split_returned_error(output) {
+        return (if stdout.is_empty() { None } else { Some(stdout) }, error);
+    }
+
+    if let Some(i) = output.find("note: test did not panic as expected") {
+        let stdout = output[..i].trim_end();
+        return (if stdout.is_empty() { None } else { Some(stdout) }, output[i..].trim_end());
+    }
+
+    match output.find(&format!("thread '{}'", name)) {
+        Some(0) | None => (None, trim_backtrace_note(output)),
+        Some(i) => (Some(output[..i].trim_end()), trim_backtrace_note(&output[i..])),
+    }
+}
+
+named!(
+    captured_test<Test<'a, 'a, 'a>>,
+    do_parse!(
+        tag!("test") >>
+        space >>
+        name: map!(
+            verify!(
+                map_res!(
+                    take_until_s!(" ..."),
+                    str::from_utf8
+                ),
+                |name: &str| !name.contains('\n')
+            ),
+            strip_should_panic
+        ) >>
+        tag!(" ... ") >>
+        not!(
+            alt_complete!(
+                ok_or_failed | ignored |
+                map!(
+                    alt!(tag!("test ") | tag!("error: test failed")),
+                    |_| ""
+                )
+            )
+        ) >>
+        captured: captured_output >>
+        (match captured.1 {
+            "fail" => {
+                let (output, backtrace) = split_backtrace(captured.0);
+                let (stdout, error) = split_panic_message(name, output);
+                Test {
+                    name,
+                    status: captured.1,
+                    error: Some(error),
+                    slow_warning: false,
+                    stdout,
+                    location: panic_location(error),
+                    assertion: parse_assertion(error),
+                    backtrace,
+                    failure_kind: Some(failure_kind(captured.0)),
+                    duration: None,
+                    retries: 0
+                }
+            }
+            _ => Test {
+                name,
+                status: captured.1,
+                error: None,
+                slow_warning: false,
+                stdout: Some(captured.0),
+                location: None,
+                assertion: None,
+                backtrace: vec![],
+                failure_kind: None,
+                duration: None,
+                retries: 0
+            },
         })
     )
 );
 
+fn is_test_output(line: &str) -> bool {
+    let line = line.trim_start();
+
+    !(line.starts_with("test ") || line.starts_with("failures:") || line.starts_with("successes:") ||
+        line.starts_with("error: test failed"))
+}
+
+// Output from tests running in parallel with `--nocapture` can't be tied to
+// any one of them, so it's skipped over. Within the test list, a line that
+// looks like a `Running` header is output as well.
+named!(
+    test_output<&'a str>,
+    verify!(any_line, is_test_output)
+);
+
 named!(
-    test_results<Vec<Test> >,
-    many0!(
-        test_result
+    test_results<Vec<Test<'a, 'a, 'a>> >,
+    map!(
+        many0!(
+            alt_complete!(
+                map!(slow_warning, Some) |
+                map!(captured_test, Some) |
+                map!(test_result, Some) |
+                map!(test_output, |_| None)
+            )
+        ),
+        |xs: Vec<Option<Test<'a, 'a, 'a>>>| merge_slow_warnings(xs.into_iter().flatten().collect())
     )
 );
 
@@ -153,7 +478,7 @@ pub struct SuiteResult<'a> {
 }
 
 named!(
-    suite_result<SuiteResult>,
+    suite_result<SuiteResult<'a>>,
     do_parse!(
         ws!(tag!("test result: ")) >>
         state: ok_or_failed >>
@@ -168,19 +493,29 @@ named!(
         tag!("measured;") >>
         digits >>
         ws!(tag!("filtered out")) >>
+        opt!(
+            complete!(
+                do_parse!(
+                    tag!("; finished in") >>
+                    call!(nom::not_line_ending) >>
+                    opt!(complete!(nom::multispace)) >>
+                    ()
+                )
+            )
+        ) >>
         (SuiteResult {
-          state:state,
-          passed:passed,
-          failed:failed,
-          ignored:ignored,
+          state,
+          passed,
+          failed,
+          ignored,
           total: passed + failed + ignored,
-          measured:measured
+          measured
         })
     )
 );
 
 named!(
-    fail_line<&str>,
+    fail_line<&'a str>,
     do_parse!(
         ws!(tag!("----")) >>
         name: map_res!(
@@ -197,38 +532,130 @@ named!(
 pub struct Failure<'a, 'b> {
     pub name: &'a str,
     pub error: &'b str,
+    /// The frames of the `stack backtrace:` printed after the panic, if any.
+    pub backtrace: Vec<Frame<'b>>,
+    pub failure_kind: FailureKind<'b>,
+}
+
+// Drops the hint about `RUST_BACKTRACE` that follows a panic message.
+fn trim_backtrace_note(text: &str) -> &str {
+    let text = text.trim_end();
+
+    match text.rfind('\n') {
+        Some(i) if text[i + 1..].starts_with("note: ") && text[i + 1..].contains("RUST_BACKTRACE") => {
+            text[..i].trim_end()
+        }
+        None if text.starts_with("note: ") && text.contains("RUST_BACKTRACE") => "",
+        _ => text,
+    }
+}
+
+// Whether a line ends the output of a `---- name stdout ----` block.
+fn ends_output_block(line: &[u8]) -> bool {
+    let line = match str::from_utf8(line) {
+        Ok(x) => x.trim(),
+        Err(_) => return false,
+    };
+
+    line == "failures:" || line == "successes:" || line.starts_with("test result: ") ||
+        (line.starts_with("---- ") && line.ends_with(" stdout ----"))
+}
+
+// The text of an output block, which runs until the next block or the list
+// of names that closes the section.
+fn output_block(input: &[u8]) -> IResult<&[u8], &str> {
+    let mut end = 0;
+
+    while end < input.len() {
+        let line_end = input[end..]
+            .iter()
+            .position(|&c| c == b'\n')
+            .map_or(input.len(), |i| end + i + 1);
+
+        if ends_output_block(&input[end..line_end]) {
+            break;
+        }
+
+        end = line_end;
+    }
+
+    match str::from_utf8(&input[..end]) {
+        Ok(x) => IResult::Done(&input[end..], trim_backtrace_note(x.trim_start())),
+        Err(_) => IResult::Error(error_position!(nom::ErrorKind::MapRes, input)),
+    }
 }
 
 named!(
-    failure<Failure>,
+    failure<Failure<'a, 'a>>,
     do_parse!(
         name: fail_line >>
-        error: rest_of_line >>
-        opt!(
-            tag!("note: Run with `RUST_BACKTRACE=1` for a backtrace.")
-        ) >>
-        line_ending >>
-        line_ending >>
-        (Failure {
-            name:name,
-            error:error
+        output: output_block >>
+        ({
+            let (error, backtrace) = split_backtrace(output);
+            Failure {
+                name,
+                error,
+                backtrace,
+                failure_kind: failure_kind(output)
+            }
         })
     )
 );
 
-named!(failures<Vec<Failure> >, many1!(failure));
+named!(failures<Vec<Failure<'a, 'a>> >, many1!(failure));
+
+// The names of the failed tests, listed under a second `failures:` header
+// after their output. The list is looked for on its own, so that it
+// survives output blocks that didn't parse.
+fn failure_names(section: &[u8]) -> Vec<&str> {
+    let lines: Vec<&str> = match str::from_utf8(section) {
+        Ok(x) => x.lines().collect(),
+        Err(_) => return vec![],
+    };
+
+    match lines.iter().rposition(|x| x.trim() == "failures:") {
+        Some(i) => {
+            lines[i + 1..]
+                .iter()
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty())
+                .collect()
+        }
+        None => vec![],
+    }
+}
 
-named!(fail_opt<Option<Vec<Failure> > >,
+named!(fail_opt<Option<(Vec<Failure<'a, 'a>>, Vec<&'a str>)> >,
     opt!(
         do_parse!(
             ws!(
                 tag!("failures:")
             ) >>
-            f: failures >>
-            take_until!(
+            f: many0!(failure) >>
+            rest: take_until!(
                 "test result: "
             ) >>
-            (f)
+            ((f, failure_names(rest)))
+        )
+    )
+);
+
+// The `successes:` section from `--show-output`, which lists what passing
+// tests printed in the same format as failures.
+named!(success_opt<Option<Vec<Failure<'a, 'a>> > >,
+    opt!(
+        do_parse!(
+            ws!(tag!("successes:")) >>
+            s: many0!(failure) >>
+            ws!(tag!("successes:")) >>
+            many0!(
+                do_parse!(
+                    not!(alt_complete!(tag!("failures:") | tag!("test result: "))) >>
+                    any_line >>
+                    ()
+                )
+            ) >>
+            (s)
         )
     )
 );
@@ -242,28 +669,199 @@ pub struct Suite<'a, 'b, 'c, 'd, 'e> {
     pub ignored: i64,
     pub measured: i64,
     pub total: i64,
+    /// The count from `running N tests`, known before any test reports.
+    pub planned: i64,
+    /// Tests announced by `running N tests` that never reported a result.
+    pub missing: i64,
+    pub slow: Vec<&'c str>,
+    /// The failed tests as listed at the end of the `failures:` section.
+    pub failure_names: Vec<&'e str>,
+    /// The arguments cargo suggests for rerunning this target, like
+    /// `["--test", "foo"]`.
+    pub rerun_args: Vec<&'a str>,
+    /// Inconsistencies between the listed tests and the `running N tests` and
+    /// `test result:` lines, which usually point at a misparse, and problems
+    /// like leaked handles or shards that disagree about a test.
+    pub warnings: Vec<String>,
     pub tests: Vec<Test<'c, 'd, 'e>>,
 }
 
+impl<'a, 'b, 'c, 'd, 'e> Suite<'a, 'b, 'c, 'd, 'e> {
+    /// Builds the command that reruns just the tests that failed, as in
+    /// `cargo test --test foo -- it_fails --exact`. The tests are the ones
+    /// listed under `failures:`, or without that list, as in a crashed
+    /// suite, the ones that failed or never finished. Returns `None` without
+    /// a rerun hint or failed tests.
+    pub fn rerun_command(&self) -> Option<Vec<String>> {
+        let names: Vec<&str> = if self.failure_names.is_empty() {
+            self.tests
+                .iter()
+                .filter(|t| t.status == "fail" || t.status == "incomplete")
+                .map(|t| t.name)
+                .collect()
+        } else {
+            self.failure_names.clone()
+        };
+
+        if self.rerun_args.is_empty() || names.is_empty() {
+            return None;
+        }
+
+        let mut command = vec!["cargo".to_string(), "test".to_string()];
+        command.extend(self.rerun_args.iter().map(|x| x.to_string()));
+        command.push("--".to_string());
+        command.extend(names.iter().map(|x| x.to_string()));
+        command.push("--exact".to_string());
+
+        Some(command)
+    }
+}
+
 fn find_message_by_name<'a, 'b>(name: &str, failures: &Vec<Failure<'a, 'b>>) -> Option<&'b str> {
     failures.iter().find(|x| x.name == name).map(|x| x.error)
 }
 
+// A slow warning and the result line for the same test both show up as
+// entries, so fold them into one. In single-threaded runs the result line comes
+// first without a verdict and the verdict trails the warning instead.
+fn merge_slow_warnings<'a, 'b, 'c>(tests: Vec<Test<'a, 'b, 'c>>) -> Vec<Test<'a, 'b, 'c>> {
+    let mut merged: Vec<Test> = Vec::with_capacity(tests.len());
+    let mut pending: HashMap<&str, usize> = HashMap::new();
+
+    for t in tests {
+        if let Some(&i) = pending.get(t.name) {
+            let x = &mut merged[i];
+            x.slow_warning = x.slow_warning || t.slow_warning;
+            if x.status == "incomplete" {
+                x.status = t.status;
+            }
+            continue;
+        }
+
+        if t.slow_warning || t.status == "incomplete" {
+            pending.insert(t.name, merged.len());
+        }
+
+        merged.push(t);
+    }
+
+    merged
+}
+
+fn count_status(tests: &[Test], status: &str) -> i64 {
+    tests.iter().filter(|t| t.status == status).count() as i64
+}
+
+fn validate_counts(
+    planned: i64,
+    tests: &[Test],
+    failures: &Option<Vec<Failure>>,
+    failure_names: &[&str],
+    result: &SuiteResult,
+) -> Vec<String> {
+    let mut warnings = vec![];
+
+    let listed = tests.len() as i64;
+    if listed != planned {
+        warnings.push(format!(
+            "`running {} tests` but {} tests were listed",
+            planned,
+            listed
+        ));
+    }
+
+    let counts = [
+        ("passed", "pass", result.passed),
+        ("failed", "fail", result.failed),
+        ("ignored", "ignored", result.ignored),
+        ("measured", "measured", result.measured),
+    ];
+
+    for &(label, status, expected) in counts.iter() {
+        let actual = count_status(tests, status);
+        if actual != expected {
+            warnings.push(format!(
+                "summary says {} {} but {} {} tests were listed",
+                expected,
+                label,
+                actual,
+                label
+            ));
+        }
+    }
+
+    let incomplete = count_status(tests, "incomplete");
+    if incomplete > 0 {
+        warnings.push(format!(
+            "{} tests have no result but the suite finished",
+            incomplete
+        ));
+    }
+
+    if let Some(ref xs) = *failures {
+        for f in xs.iter().filter(|f| !tests.iter().any(|t| t.name == f.name)) {
+            warnings.push(format!(
+                "failure output for `{}` does not match any listed test",
+                f.name
+            ));
+        }
+
+        for t in tests.iter().filter(|t| t.status == "fail" && !failure_names.contains(&t.name)) {
+            warnings.push(format!(
+                "`{}` failed but is not listed under `failures:`",
+                t.name
+            ));
+        }
+
+        for name in failure_names.iter().filter(|&&x| !tests.iter().any(|t| t.name == x && t.status == "fail")) {
+            warnings.push(format!(
+                "`{}` is listed under `failures:` but was not marked FAILED",
+                name
+            ));
+        }
+    }
+
+    warnings
+}
+
 fn handle_parsed_suite<'a, 'b, 'c, 'd, 'e>(
     name: &'a str,
+    planned: i64,
     tests: Vec<Test<'c, 'd, 'e>>,
-    failures: Option<Vec<Failure<'e, 'e>>>,
-    result: SuiteResult<'b>,
+    successes: Option<Vec<Failure<'e, 'e>>>,
+    failures: Option<(Vec<Failure<'e, 'e>>, Vec<&'e str>)>,
+    result: Option<SuiteResult<'b>>,
+    rerun_args: Vec<&'a str>,
 ) -> Suite<'a, 'b, 'c, 'd, 'e> {
+    let (failures, failure_names) = match failures {
+        Some((xs, names)) => (Some(xs), names),
+        None => (None, vec![]),
+    };
+
+    let warnings = match result {
+        Some(ref r) => validate_counts(planned, &tests, &failures, &failure_names, r),
+        None => vec![],
+    };
+
     let tests_with_failures = match failures {
         Some(xs) => {
             tests
-                .iter()
+                .into_iter()
                 .map(|t| {
-                    Test {
-                        error: find_message_by_name(t.name, &xs),
-                        name: t.name,
-                        status: t.status,
+                    match xs.iter().find(|x| x.name == t.name) {
+                        Some(x) => {
+                            let (stdout, error) = split_panic_message(t.name, x.error);
+                            Test {
+                                error: Some(error),
+                                stdout: stdout.or(t.stdout),
+                                location: panic_location(error),
+                                assertion: parse_assertion(error),
+                                backtrace: x.backtrace.clone(),
+                                failure_kind: Some(x.failure_kind),
+                                ..t
+                            }
+                        }
+                        None => t,
                     }
                 })
                 .collect()
@@ -271,8 +869,46 @@ fn handle_parsed_suite<'a, 'b, 'c, 'd, 'e>(
         None => tests,
     };
 
+    let tests_with_failures: Vec<Test> = match successes {
+        Some(xs) => {
+            tests_with_failures
+                .into_iter()
+                .map(|t| {
+                    Test {
+                        stdout: find_message_by_name(t.name, &xs).or(t.stdout),
+                        ..t
+                    }
+                })
+                .collect()
+        }
+        None => tests_with_failures,
+    };
+
+    // A suite without a `test result:` line crashed or was killed, so the
+    // counts have to come from the tests that made it into the output.
+    let result = result.unwrap_or_else(|| {
+        SuiteResult {
+            state: "fail",
+            passed: count_status(&tests_with_failures, "pass"),
+            failed: count_status(&tests_with_failures, "fail"),
+            ignored: count_status(&tests_with_failures, "ignored"),
+            total: tests_with_failures.len() as i64,
+            measured: 0,
+        }
+    });
+
     Suite {
-        name: name,
+        name,
+        slow: tests_with_failures
+            .iter()
+            .filter(|t| t.slow_warning)
+            .map(|t| t.name)
+            .collect(),
+        planned,
+        missing: (planned - tests_with_failures.len() as i64).max(0),
+        failure_names,
+        rerun_args,
+        warnings,
         tests: tests_with_failures,
         state: result.state,
         total: result.total,
@@ -283,401 +919,2761 @@ fn handle_parsed_suite<'a, 'b, 'c, 'd, 'e>(
     }
 }
 
+// Arguments cargo quotes for rerunning a target, like `'--test foo'`. Older
+// versions quote with `'`, newer ones with a backtick.
 named!(
-    suite_parser<Suite>,
+    quoted_args<Vec<&'a str> >,
     do_parse!(
-        name: suite_line >>
-        suite_count >>
-        tests: test_results >>
-        failures: fail_opt >>
-        result: suite_result >>
-        (handle_parsed_suite(name, tests, failures, result))
+        alt!(char!('\'') | char!('`')) >>
+        args: map_res!(
+            is_not!("'`\n"),
+            str::from_utf8
+        ) >>
+        alt!(char!('\'') | char!('`')) >>
+        (args.split_whitespace().collect())
     )
 );
 
 named!(
-    suites_parser<Vec<Suite > >,
-    many1!(suite_parser)
-);
-
-named!(
-  compile_error<Vec<Suite > >,
-  do_parse!(
-    ws!(tag!("error")) >>
-    opt_res!(
-      do_parse!(
-        char!('[') >>
-        take_until_and_consume!("]") >>
-        ()
-      )
-    ) >>
-    ws!(char!(':')) >>
-    error: map_res!(
-            take_till!(|c| c == 0x0),
-            str::from_utf8
-        ) >>
-    (vec![Suite {
-        name: "unknown",
-        state: "fail",
-        total: 1,
-        passed: 0,
-        failed: 1,
-        ignored: 0,
-        measured: 0,
-        tests: vec![
-          Test {
-            name: "compile failed",
-            status: "fail",
-            error: Some(error.into())
-          }
-        ]
-    }])
-  )
+    rerun_hint<Vec<&'a str> >,
+    do_parse!(
+        ws!(tag!("error: test failed, to rerun pass")) >>
+        args: quoted_args >>
+        opt!(complete!(nom::multispace)) >>
+        (args)
+    )
 );
 
+// `--no-fail-fast` saves the hints for the end, one per failed target.
 named!(
-    pub cargo_test_result_parser<Vec<Suite > >,
+    targets_failed<Vec<Vec<&'a str> > >,
     do_parse!(
-        many0!(
-          alt!(updating | downloading | installing | compiling | finished)
-        ) >>
-        suites: alt!(suites_parser | compile_error) >>
-        (suites)
+        ws!(tag!("error:")) >>
+        digits >>
+        tag!("targets failed:") >>
+        args: many1!(ws!(quoted_args)) >>
+        (args)
     )
 );
 
+// A crashed suite's hint trails the test that was running, so it ends up
+// among the lines skipped after the crash.
+fn find_rerun_hint<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    lines
+        .iter()
+        .filter_map(|line| match complete!(line.as_bytes(), rerun_hint) {
+            IResult::Done(_, x) => Some(x),
+            _ => None,
+        })
+        .next()
+        .unwrap_or_default()
+}
 
-#[cfg(test)]
-mod parser_tests {
-    use nom::IResult;
-    use std::fmt::Debug;
-    use super::{downloading, compiling, installing, finished, suite_line, suite_count,
-                ok_or_failed, Test, test_result, test_results, digits, suite_result, SuiteResult,
-                cargo_test_result_parser, Suite, fail_line, failure, Failure, failures};
-
-    fn assert_done<R: PartialEq + Debug>(l: IResult<&[u8], R>, r: R) {
-        assert_eq!(
-          l,
-          IResult::Done(&b""[..], r)
-      )
+// `Running unittests src/lib.rs (target/debug/deps/foo-...)` on newer cargo
+// names the binary in parentheses.
+fn binary_path(suite: &str) -> &str {
+    match (suite.rfind(" ("), suite.ends_with(')')) {
+        (Some(i), true) => &suite[i + 2..suite.len() - 1],
+        _ => suite,
     }
+}
 
-    #[test]
-    fn it_should_parse_a_downloading_line() {
-        let output = &b" Downloading nvpair-sys v0.1.0
-"[..];
+// The target a test binary was built from, like `foo` for
+// `target/debug/deps/foo-5a7be5d1b9c8e0f6`. Doc-tests have no binary.
+fn binary_target(suite: &str) -> Option<&str> {
+    let path = binary_path(suite);
+    let file = path.rsplit(['/', '\\']).next()?;
 
-        assert_done(downloading(output), ())
+    if file.len() == path.len() {
+        return None;
     }
 
-    #[test]
-    fn it_should_parse_an_installing_line() {
-        let output = &b" Installing cargo-test-junit v0.6.2
-"[..];
+    let file = file.strip_suffix(".exe").unwrap_or(file);
 
-        assert_done(installing(output), ())
+    match file.rsplit_once('-') {
+        Some((name, hash)) if !hash.is_empty() && hash.bytes().all(|c| c.is_ascii_hexdigit()) => {
+            Some(name)
+        }
+        _ => Some(file),
     }
+}
 
-    #[test]
-    fn it_should_match_a_compiler_line() {
-        let output = &b"   Compiling docker-command v0.1.0 (file:///Users/joegrund/projects/docker-command-rs)
-"
-            [..];
+// Whether a hint like `--test foo`, `-p foo --lib` or `--doc` reruns a suite.
+fn hint_matches(args: &[&str], suite: &Suite) -> bool {
+    let value = |flag| {
+        args.iter()
+            .position(|&x| x == flag)
+            .and_then(|i| args.get(i + 1))
+            .map(|x| x.replace('-', "_"))
+    };
 
-        assert_done(compiling(output), ());
-    }
+    let target = match binary_target(suite.name) {
+        Some(x) => x,
+        None => return args.contains(&"--doc"),
+    };
 
-    #[test]
-    fn it_should_parse_finish_line() {
-        let result = finished(
-            &b"    Finished debug [unoptimized + debuginfo] target(s) in 0.0 secs
-"[..],
-        );
+    if args.contains(&"--doc") {
+        return false;
+    }
 
-        assert_done(result, ());
+    if let Some(name) = value("--test").or_else(|| value("--bin")) {
+        return name == target;
     }
 
-    #[test]
-    fn it_should_parse_suite_line() {
-        let result = suite_line(
-            &b"Running target/debug/deps/docker_command-be014e20fbd07382
-"[..],
-        );
+    // That leaves `--lib`. Newer cargo names the source of unit tests, while
+    // older cargo gives nothing to tell them from a binary's.
+    let is_lib = if suite.name.ends_with(')') {
+        suite.name.starts_with("unittests ") && !suite.name.starts_with("unittests src/main.rs") &&
+            !suite.name.starts_with("unittests src/bin/")
+    } else {
+        true
+    };
+
+    let package = value("-p").or_else(|| value("--package"));
+
+    is_lib && (package.is_none() || package.as_deref() == Some(target))
+}
+
+// Hints listed together at the end go to the failed suites they name, in
+// order, so a failed suite without a hint doesn't shift the rest.
+fn attach_rerun_hints<'a>(
+    mut suites: Vec<Suite<'a, 'a, 'a, 'a, 'a>>,
+    hints: Vec<Vec<&'a str>>,
+) -> Vec<Suite<'a, 'a, 'a, 'a, 'a>> {
+    for args in hints {
+        let suite = suites
+            .iter_mut()
+            .find(|x| x.state == "fail" && x.rerun_args.is_empty() && hint_matches(&args, x));
+
+        if let Some(x) = suite {
+            x.rerun_args = args;
+        }
+    }
+
+    suites
+}
+
+named!(
+    aborted_line<&'a str>,
+    do_parse!(
+        not!(suite_line) >>
+        line: any_line >>
+        (line)
+    )
+);
+
+named!(
+    aborted<Vec<&'a str> >,
+    many0!(aborted_line)
+);
+
+// How a suite ends: its `successes:` and `failures:` sections, its
+// `test result:` line, the arguments to rerun it, and a warning when the
+// output in between could not be parsed.
+type SuiteEnd<'a> = (
+    Option<Vec<Failure<'a, 'a>>>,
+    Option<(Vec<Failure<'a, 'a>>, Vec<&'a str>)>,
+    Option<SuiteResult<'a>>,
+    Vec<&'a str>,
+    Option<String>,
+);
+
+// A suite without a `test result:` line up to the next suite crashed. With
+// one, it finished but the output before it didn't parse, so the summary
+// still stands and the suite is flagged rather than reported as crashed.
+fn unparsed_end<'a>(lines: Vec<&'a str>) -> SuiteEnd<'a> {
+    let result = lines
+        .iter()
+        .filter_map(|line| match complete!(line.as_bytes(), suite_result) {
+            IResult::Done(_, x) => Some(x),
+            _ => None,
+        })
+        .next();
+
+    let warning = result
+        .as_ref()
+        .map(|_| "the output before `test result:` could not be parsed".to_string());
+
+    (None, None, result, find_rerun_hint(&lines), warning)
+}
+
+fn add_warning<'a>(
+    mut suite: Suite<'a, 'a, 'a, 'a, 'a>,
+    warning: Option<String>,
+) -> Suite<'a, 'a, 'a, 'a, 'a> {
+    suite.warnings.extend(warning);
+    suite
+}
+
+named!(
+    suite_parser<Suite<'a, 'a, 'a, 'a, 'a>>,
+    do_parse!(
+        name: suite_line >>
+        planned: suite_count >>
+        tests: test_results >>
+        end: alt_complete!(
+            do_parse!(
+                successes: success_opt >>
+                failures: fail_opt >>
+                result: suite_result >>
+                rerun: opt!(complete!(rerun_hint)) >>
+                ((successes, failures, Some(result), rerun.unwrap_or_default(), None))
+            ) |
+            map!(aborted, unparsed_end)
+        ) >>
+        (add_warning(handle_parsed_suite(name, planned, tests, end.0, end.1, end.2, end.3), end.4))
+    )
+);
+
+named!(
+    suites_parser<Vec<Suite<'a, 'a, 'a, 'a, 'a> > >,
+    do_parse!(
+        suites: many1!(suite_parser) >>
+        hints: opt!(complete!(targets_failed)) >>
+        (attach_rerun_hints(suites, hints.unwrap_or_default()))
+    )
+);
+
+#[derive(Debug, PartialEq)]
+pub struct SourceLocation<'a> {
+    pub file: Cow<'a, str>,
+    pub line: i64,
+    pub column: Option<i64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic<'a> {
+    /// The error code (`E0369`) for errors, or the lint name for warnings.
+    pub code: Option<Cow<'a, str>>,
+    pub message: Cow<'a, str>,
+    pub location: Option<SourceLocation<'a>>,
+    pub notes: Vec<Cow<'a, str>>,
+    pub crate_name: Option<Cow<'a, str>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BuildFailure<'a> {
+    pub crate_name: Option<Cow<'a, str>>,
+    pub diagnostics: Vec<Diagnostic<'a>>,
+}
+
+enum BuildItem<'a> {
+    Error(Diagnostic<'a>),
+    Warning(Diagnostic<'a>),
+    Failed(&'a str),
+    Generated(&'a str, Option<usize>),
+    Artifact(String, String),
+    Unsuccessful,
+    Other,
+}
+
+// Accepts `file:line:column`, or `file:line` as older panics print it.
+fn parse_source_location<'a>(x: &'a str) -> Option<SourceLocation<'a>> {
+    let mut parts = x.rsplitn(3, ':');
+    let last = parts.next()?.parse().ok()?;
+
+    let (file, line, column) = match parts.next().map(str::parse) {
+        Some(Ok(line)) => (parts.next()?, line, Some(last)),
+        _ => (x.rsplit_once(':')?.0, last, None),
+    };
+
+    if file.is_empty() {
+        return None;
+    }
+
+    Some(SourceLocation {
+        file: file.into(),
+        line,
+        column,
+    })
+}
+
+// `panicked at src/lib.rs:16:5:` since 1.73, and `panicked at 'message',
+// src/lib.rs:16` before, where the message can run over several lines. A
+// `#[should_panic]` test that returned points at the test itself instead.
+fn panic_location(error: &str) -> Option<SourceLocation<'_>> {
+    let did_not_panic = "test did not panic as expected at ";
+
+    if let Some(i) = error.find(did_not_panic) {
+        return parse_source_location(error[i + did_not_panic.len()..].lines().next()?.trim());
+    }
+
+    let start = error.find("panicked at ")? + "panicked at ".len();
+    let rest = &error[start..];
+
+    let location = if rest.starts_with('\'') {
+        let i = rest.rfind("', ")?;
+        rest[i + "', ".len()..].lines().next()?
+    } else {
+        rest.lines().next()?.trim_end_matches(':')
+    };
+
+    parse_source_location(location.trim())
+}
+
+// Lints don't print a code, only a note like `#[warn(unused_variables)]` on by default.
+fn lint_name(note: &str) -> Option<&str> {
+    ["#[warn(", "#[deny(", "#[forbid("]
+        .iter()
+        .filter_map(|x| note.find(x).map(|i| &note[i + x.len()..]))
+        .next()
+        .and_then(|x| x.find(')').map(|i| &x[..i]))
+}
+
+fn handle_diagnostic<'a>(
+    level: &'a [u8],
+    code: Option<&'a str>,
+    message: &'a str,
+    body: Vec<&'a str>,
+) -> BuildItem<'a> {
+    let lines: Vec<&str> = body.iter().map(|x| x.trim()).collect();
+    let notes: Vec<&str> = lines
+        .iter()
+        .filter_map(|x| x.strip_prefix("= "))
+        .collect();
+
+    let diagnostic = Diagnostic {
+        code: code.or_else(|| notes.iter().filter_map(|x| lint_name(x)).next())
+            .map(Cow::from),
+        message: message.into(),
+        location: lines
+            .iter()
+            .filter_map(|x| x.strip_prefix("--> "))
+            .next()
+            .and_then(parse_source_location),
+        notes: notes.into_iter().map(Cow::from).collect(),
+        crate_name: None,
+    };
+
+    if level == b"warning" {
+        BuildItem::Warning(diagnostic)
+    } else {
+        BuildItem::Error(diagnostic)
+    }
+}
+
+// Package ids are `foo 0.1.0 (path+file:///foo)` on older cargo and
+// `path+file:///foo#0.1.0` or `registry+https://...#foo@0.1.0` on newer ones.
+fn package_name(id: &str) -> &str {
+    if let Some(i) = id.find(' ') {
+        return &id[..i];
+    }
+
+    let (url, fragment) = match id.find('#') {
+        Some(i) => (&id[..i], &id[i + 1..]),
+        None => (id, ""),
+    };
+
+    match fragment.find('@') {
+        Some(i) => &fragment[..i],
+        None => url.rsplit('/').next().unwrap_or(url),
+    }
+}
+
+fn json_str<'a>(value: &'a serde_json::Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(|x| x.as_str())
+}
+
+fn json_diagnostic<'a>(record: &serde_json::Value) -> Option<BuildItem<'a>> {
+    let message = record.get("message")?;
+    let level = json_str(message, "level")?;
+    let text = json_str(message, "message")?;
+
+    let is_summary = text.starts_with("aborting due to") ||
+        text.ends_with("warning emitted") ||
+        text.ends_with("warnings emitted");
+
+    if is_summary {
+        return Some(BuildItem::Other);
+    }
+
+    let location = message
+        .get("spans")
+        .and_then(|x| x.as_array())
+        .and_then(|xs| {
+            xs.iter().find(|x| {
+                x.get("is_primary").and_then(|x| x.as_bool()) == Some(true)
+            })
+        })
+        .and_then(|x| {
+            Some(SourceLocation {
+                file: json_str(x, "file_name")?.to_string().into(),
+                line: x.get("line_start")?.as_i64()?,
+                column: x.get("column_start").and_then(|x| x.as_i64()),
+            })
+        });
+
+    let notes = message
+        .get("children")
+        .and_then(|x| x.as_array())
+        .map(|xs| {
+            xs.iter()
+                .filter_map(|x| {
+                    Some(format!("{}: {}", json_str(x, "level")?, json_str(x, "message")?).into())
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let diagnostic = Diagnostic {
+        code: message
+            .get("code")
+            .and_then(|x| json_str(x, "code"))
+            .map(|x| x.to_string().into()),
+        message: text.to_string().into(),
+        location,
+        notes,
+        crate_name: json_str(record, "package_id").map(|x| package_name(x).to_string().into()),
+    };
+
+    match level {
+        "warning" => Some(BuildItem::Warning(diagnostic)),
+        x if x.starts_with("error") => Some(BuildItem::Error(diagnostic)),
+        _ => Some(BuildItem::Other),
+    }
+}
+
+fn handle_json_message<'a>(line: &str) -> Option<BuildItem<'a>> {
+    let record: serde_json::Value = serde_json::from_str(line).ok()?;
+
+    match json_str(&record, "reason")? {
+        "compiler-message" => json_diagnostic(&record),
+        "compiler-artifact" => {
+            let is_test = record
+                .get("profile")
+                .and_then(|x| x.get("test"))
+                .and_then(|x| x.as_bool()) == Some(true);
+
+            match (json_str(&record, "executable"), json_str(&record, "package_id")) {
+                (Some(path), Some(id)) if is_test => {
+                    Some(BuildItem::Artifact(path.to_string(), id.to_string()))
+                }
+                _ => Some(BuildItem::Other),
+            }
+        }
+        "build-finished" => {
+            match record.get("success").and_then(|x| x.as_bool()) {
+                Some(false) => Some(BuildItem::Unsuccessful),
+                _ => Some(BuildItem::Other),
+            }
+        }
+        _ => Some(BuildItem::Other),
+    }
+}
+
+// Cargo names the crate after its diagnostics, so fill it in backwards
+// for the ones that don't have it yet. Given how many warnings the crate
+// printed, only that many are filled in, skipping cargo's own warnings like
+// `unused manifest key`, which are a single line without a location or notes.
+fn attribute_crate<'a>(diagnostics: &mut [Diagnostic<'a>], name: &'a str, count: Option<usize>) {
+    let unattributed = diagnostics
+        .iter_mut()
+        .rev()
+        .take_while(|x| x.crate_name.is_none())
+        .filter(|x| count.is_none() || x.location.is_some() || !x.notes.is_empty());
+
+    for x in unattributed.take(count.unwrap_or(usize::MAX)) {
+        x.crate_name = Some(name.into());
+    }
+}
+
+fn handle_test_run<'a>(
+    items: Vec<BuildItem<'a>>,
+    suites: Vec<Suite<'a, 'a, 'a, 'a, 'a>>,
+) -> TestRun<'a> {
+    let mut errors = vec![];
+    let mut warnings = vec![];
+    let mut artifacts = HashMap::new();
+    let mut failed = None;
+    let mut unsuccessful = false;
+
+    for item in items {
+        match item {
+            BuildItem::Error(x) => errors.push(x),
+            BuildItem::Warning(x) => warnings.push(x),
+            BuildItem::Failed(x) => {
+                attribute_crate(&mut errors, x, None);
+                failed = failed.or(Some(x));
+            }
+            BuildItem::Generated(x, count) => attribute_crate(&mut warnings, x, count),
+            BuildItem::Artifact(path, id) => {
+                artifacts.insert(path, id);
+            }
+            BuildItem::Unsuccessful => unsuccessful = true,
+            BuildItem::Other => {}
+        }
+    }
+
+    let build_failure = if errors.is_empty() && failed.is_none() && !unsuccessful {
+        None
+    } else {
+        // With `--message-format=json` there's no `could not compile` line,
+        // but the errors name their package.
+        let crate_name = failed
+            .map(Cow::from)
+            .or_else(|| errors.iter().filter_map(|x| x.crate_name.clone()).next());
+
+        Some(BuildFailure {
+            crate_name,
+            diagnostics: errors,
+        })
+    };
+
+    TestRun {
+        suites,
+        build_failure,
+        warnings,
+        artifacts,
+    }
+}
+
+fn has_build_errors(items: &[BuildItem]) -> bool {
+    items
+        .iter()
+        .any(|x| matches!(*x, BuildItem::Error(_) | BuildItem::Unsuccessful))
+}
+
+// Everything rustc prints under a diagnostic header is indented or starts
+// with a line number, while cargo's own messages are a single line.
+named!(
+    diagnostic_body_line<&'a str>,
+    verify!(
+        any_line,
+        |line: &str| {
+            !line.trim().is_empty() &&
+                line.starts_with(|c: char| c.is_whitespace() || c.is_ascii_digit())
+        }
+    )
+);
+
+named!(
+    diagnostic<BuildItem<'a>>,
+    do_parse!(
+        level: alt!(tag!("error") | tag!("warning")) >>
+        code: opt!(
+            complete!(
+                delimited!(
+                    char!('['),
+                    map_res!(take_until!("]"), str::from_utf8),
+                    char!(']')
+                )
+            )
+        ) >>
+        tag!(": ") >>
+        message: any_line >>
+        body: many0!(diagnostic_body_line) >>
+        (handle_diagnostic(level, code, message, body))
+    )
+);
+
+// `cargo test --message-format=json` mixes these records in with the
+// human readable status lines and libtest output.
+named!(
+    json_message<BuildItem<'a>>,
+    do_parse!(
+        opt!(complete!(nom::multispace)) >>
+        peek!(tag!("{\"reason\":")) >>
+        item: map_opt!(any_line, handle_json_message) >>
+        (item)
+    )
+);
+
+named!(
+    warning<BuildItem<'a>>,
+    do_parse!(
+        opt!(complete!(nom::multispace)) >>
+        peek!(tag!("warning")) >>
+        item: diagnostic >>
+        (item)
+    )
+);
+
+named!(
+    could_not_compile<&'a str>,
+    do_parse!(
+        tag!("error: ") >>
+        alt!(tag!("Could not compile `") | tag!("could not compile `")) >>
+        name: map_res!(
+            take_until!("`"),
+            str::from_utf8
+        ) >>
+        any_line >>
+        (name)
+    )
+);
+
+// `generated 3 warnings (1 duplicate)` means only two were printed.
+fn printed_warnings(summary: &str) -> Option<usize> {
+    let generated: usize = summary.split_whitespace().next()?.parse().ok()?;
+
+    let duplicates = summary
+        .find(" duplicate")
+        .and_then(|i| summary[..i].rsplit('(').next())
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(0);
+
+    Some(generated.saturating_sub(duplicates))
+}
+
+named!(
+    warnings_generated<(&'a str, Option<usize>)>,
+    do_parse!(
+        opt!(complete!(nom::multispace)) >>
+        generated: flat_map!(
+            call!(nom::not_line_ending),
+            complete!(
+                do_parse!(
+                    tag!("warning: `") >>
+                    name: map_res!(
+                        take_until_s!("`"),
+                        str::from_utf8
+                    ) >>
+                    take_until_s!(" generated ") >>
+                    tag!(" generated ") >>
+                    summary: map_res!(
+                        call!(nom::rest),
+                        str::from_utf8
+                    ) >>
+                    ((name, printed_warnings(summary)))
+                )
+            )
+        ) >>
+        opt!(complete!(line_ending)) >>
+        (generated)
+    )
+);
+
+named!(
+    build_noise<()>,
+    do_parse!(
+        opt!(complete!(nom::multispace)) >>
+        alt!(
+            tag!("error: aborting due to") |
+            tag!("error: test failed") |
+            do_parse!(
+                tag!("error: ") >>
+                digits >>
+                tag!("targets failed") >>
+                (&b""[..])
+            ) |
+            tag!("warning: build failed") |
+            do_parse!(
+                tag!("warning: ") >>
+                digits >>
+                alt!(tag!("warning emitted") | tag!("warnings emitted")) >>
+                (&b""[..])
+            ) |
+            tag!("For more information about") |
+            tag!("Some errors have detailed explanations") |
+            tag!("To learn more, run the command again")
+        ) >>
+        any_line >>
+        ()
+    )
+);
+
+named!(
+    preamble_item<BuildItem<'a>>,
+    alt!(
+        map!(
+            alt!(updating | downloading | installing | compiling | finished),
+            |_| BuildItem::Other
+        ) |
+        map!(warnings_generated, |(name, count)| BuildItem::Generated(name, count)) |
+        map!(build_noise, |_| BuildItem::Other) |
+        json_message |
+        warning
+    )
+);
+
+named!(
+    build_item<BuildItem<'a>>,
+    alt_complete!(
+        map!(could_not_compile, BuildItem::Failed) |
+        map!(warnings_generated, |(name, count)| BuildItem::Generated(name, count)) |
+        map!(build_noise, |_| BuildItem::Other) |
+        json_message |
+        diagnostic |
+        map!(any_line, |_| BuildItem::Other)
+    )
+);
+
+named!(
+    compile_error<Vec<BuildItem<'a>> >,
+    do_parse!(
+        opt!(complete!(nom::multispace)) >>
+        peek!(tag!("error")) >>
+        items: many1!(build_item) >>
+        (items)
+    )
+);
+
+#[derive(Debug, PartialEq)]
+pub struct TestRun<'a> {
+    pub suites: Vec<Suite<'a, 'a, 'a, 'a, 'a>>,
+    pub build_failure: Option<BuildFailure<'a>>,
+    /// Compiler warnings emitted while building the tests.
+    pub warnings: Vec<Diagnostic<'a>>,
+    /// Test binary paths mapped to their package ids, only available with
+    /// `--message-format=json`.
+    pub artifacts: HashMap<String, String>,
+}
+
+impl<'a> TestRun<'a> {
+    /// Looks up the package id of the binary a suite ran from.
+    pub fn package_id(&self, suite: &Suite) -> Option<&str> {
+        let path = binary_path(suite.name);
+
+        let file_name = Path::new(path).file_name();
+
+        // The same binary can turn up in more than one target directory, so
+        // take the closest match, and the first path among equally close ones.
+        self.artifacts
+            .iter()
+            .filter_map(|(k, v)| {
+                let rank = if k == path {
+                    0
+                } else if k.ends_with(&format!("/{}", path)) {
+                    1
+                } else if file_name.is_some() && Path::new(k).file_name() == file_name {
+                    2
+                } else {
+                    return None;
+                };
+
+                Some((rank, k, v))
+            })
+            .min()
+            .map(|(_, _, v)| v.as_str())
+    }
+}
+
+named!(
+    test_run<TestRun<'a>>,
+    do_parse!(
+        preamble: many0!(preamble_item) >>
+        run: alt_complete!(
+            suites_parser => { |suites| (suites, vec![]) } |
+            compile_error => { |items| (vec![], items) } |
+            cond_reduce!(
+                has_build_errors(&preamble),
+                do_parse!(opt!(complete!(nom::multispace)) >> eof!() >> ())
+            ) => { |_| (vec![], vec![]) }
+        ) >>
+        (handle_test_run(
+            preamble.into_iter().chain(run.1).collect(),
+            run.0
+        ))
+    )
+);
+
+/// Parses the output of `cargo test` into its suites, or into the build
+/// failure that kept them from running.
+///
+/// The parsers borrow from `input` and expect `\n` line endings without
+/// color codes. A log that may have `\r\n` endings, like one saved on
+/// Windows, has to go through [`normalize`] first; otherwise the `\r`s end
+/// up in the test output and errors.
+pub fn cargo_test_result_parser(input: &[u8]) -> IResult<&[u8], TestRun<'_>> {
+    test_run(input)
+}
+
+
+/// Takes the run out of a parse that's expected to succeed.
+#[cfg(test)]
+fn parsed<'a>(result: IResult<&'a [u8], TestRun<'a>>) -> TestRun<'a> {
+    match result {
+        IResult::Done(_, x) => x,
+        x => panic!("expected a run, got {:?}", x),
+    }
+}
+
+#[cfg(test)]
+mod parser_tests {
+    use nom::IResult;
+    use std::fmt::Debug;
+    use std::collections::HashMap;
+    use super::{downloading, compiling, installing, finished, suite_line, suite_count,
+                ok_or_failed, Test, test_result, slow_warning, test_results, digits, suite_result, SuiteResult,
+                cargo_test_result_parser, normalize, panic_location, TestRun, BuildFailure, Diagnostic, SourceLocation, Suite, fail_line, failure, Failure, failures, Assertion, FailureKind, Frame,
+                warnings_generated, parsed};
+
+    fn assert_done<R: PartialEq + Debug>(l: IResult<&[u8], R>, r: R) {
+        assert_eq!(
+          l,
+          IResult::Done(&b""[..], r)
+      )
+    }
+
+    // Parses the output, checking it parses the same with Windows line endings.
+    fn parse_run(output: &[u8]) -> IResult<&[u8], TestRun<'_>> {
+        let result = cargo_test_result_parser(output);
+
+        let crlf: Vec<u8> = output
+            .iter()
+            .flat_map(|&c| if c == b'\n' { vec![b'\r', c] } else { vec![c] })
+            .collect();
+        let normalized = normalize(&crlf);
+
+        match (&result, cargo_test_result_parser(&normalized)) {
+            (&IResult::Done(rest, ref x), IResult::Done(crlf_rest, ref y)) => {
+                assert_eq!(rest, crlf_rest);
+                assert_eq!(x, y);
+            }
+            (x, y) => panic!("expected both to parse, got {:?} and {:?}", x, y),
+        }
+
+        result
+    }
+
+    #[test]
+    fn it_should_parse_a_downloading_line() {
+        let output = &b" Downloading nvpair-sys v0.1.0
+"[..];
+
+        assert_done(downloading(output), ())
+    }
+
+    #[test]
+    fn it_should_parse_an_installing_line() {
+        let output = &b" Installing cargo-test-junit v0.6.2
+"[..];
+
+        assert_done(installing(output), ())
+    }
+
+    #[test]
+    fn it_should_match_a_compiler_line() {
+        let output = &b"   Compiling docker-command v0.1.0 (file:///Users/joegrund/projects/docker-command-rs)
+"
+            [..];
+
+        assert_done(compiling(output), ());
+    }
+
+    #[test]
+    fn it_should_parse_finish_line() {
+        let result = finished(
+            &b"    Finished debug [unoptimized + debuginfo] target(s) in 0.0 secs
+"[..],
+        );
+
+        assert_done(result, ());
+    }
+
+    #[test]
+    fn it_should_parse_suite_line() {
+        let result = suite_line(
+            &b"Running target/debug/deps/docker_command-be014e20fbd07382
+"[..],
+        );
 
         assert_done(result, "target/debug/deps/docker_command-be014e20fbd07382");
     }
 
     #[test]
-    fn it_should_parse_suite_count() {
-        let result = suite_count(
-            &b"running 0 tests
-"[..],
+    fn it_should_parse_suite_count() {
+        let result = suite_count(
+            &b"running 0 tests
+"[..],
+        );
+
+        assert_done(result, 0);
+    }
+
+    #[test]
+    fn it_should_parse_a_single_test_suite_count() {
+        let result = suite_count(
+            &b"running 1 test
+"[..],
+        );
+
+        assert_done(result, 1);
+    }
+
+    #[test]
+    fn it_should_match_ok() {
+        assert_done(ok_or_failed(&b"ok"[..]), "pass");
+    }
+
+    #[test]
+    fn it_should_match_failed() {
+        assert_done(ok_or_failed(&b"FAILED"[..]), "fail");
+    }
+
+    #[test]
+    fn it_should_parse_test_result() {
+        let result = test_result(&b"test it_runs_a_command ... ok"[..]);
+
+        assert_done(
+            result,
+            Test {
+                name: "it_runs_a_command",
+                status: "pass",
+                error: None,
+                slow_warning: false,
+                stdout: None,
+                location: None,
+                assertion: None,
+                backtrace: vec![],
+                failure_kind: None,
+                duration: None,
+                retries: 0,
+            },
+        );
+    }
+
+    #[test]
+    fn it_should_parse_an_ignored_test_result() {
+        let result = test_result(&b"test it_is_slow ... ignored, takes too long
+"[..]);
+
+        assert_done(
+            result,
+            Test {
+                name: "it_is_slow",
+                status: "ignored",
+                error: None,
+                slow_warning: false,
+                stdout: None,
+                location: None,
+                assertion: None,
+                backtrace: vec![],
+                failure_kind: None,
+                duration: None,
+                retries: 0,
+            },
+        );
+    }
+
+    #[test]
+    fn it_should_parse_a_test_result_without_a_verdict() {
+        let result = test_result(&b"test it_crashes ... "[..]);
+
+        assert_done(
+            result,
+            Test {
+                name: "it_crashes",
+                status: "incomplete",
+                error: None,
+                slow_warning: false,
+                stdout: None,
+                location: None,
+                assertion: None,
+                backtrace: vec![],
+                failure_kind: None,
+                duration: None,
+                retries: 0,
+            },
+        );
+    }
+
+    #[test]
+    fn it_should_parse_a_slow_warning() {
+        let result = slow_warning(&b"test it_is_slow has been running for over 60 seconds
+"[..]);
+
+        assert_done(
+            result,
+            Test {
+                name: "it_is_slow",
+                status: "incomplete",
+                error: None,
+                slow_warning: true,
+                stdout: None,
+                location: None,
+                assertion: None,
+                backtrace: vec![],
+                failure_kind: None,
+                duration: None,
+                retries: 0,
+            },
+        );
+    }
+
+    #[test]
+    fn it_should_merge_slow_warnings_with_results() {
+        let result = test_results(
+            &b"test tests::it_is_slow has been running for over 60 seconds
+test tests::it_is_fast ... ok
+test tests::it_is_slow ... FAILED
+"
+                [..],
+        );
+
+        assert_done(
+            result,
+            vec![
+                Test {
+                    name: "tests::it_is_slow",
+                    status: "fail",
+                    error: None,
+                    slow_warning: true,
+                    stdout: None,
+                    location: None,
+                    assertion: None,
+                    backtrace: vec![],
+                    failure_kind: None,
+                    duration: None,
+                    retries: 0
+                },
+                Test {
+                    name: "tests::it_is_fast",
+                    status: "pass",
+                    error: None,
+                    slow_warning: false,
+                    stdout: None,
+                    location: None,
+                    assertion: None,
+                    backtrace: vec![],
+                    failure_kind: None,
+                    duration: None,
+                    retries: 0
+                }
+            ],
+        );
+    }
+
+    #[test]
+    fn it_should_merge_single_threaded_slow_warnings() {
+        let result = test_results(
+            &b"test tests::it_is_slow ... test tests::it_is_slow has been running for over 60 seconds
+ok
+test tests::it_is_fast ... ok
+"
+                [..],
+        );
+
+        assert_done(
+            result,
+            vec![
+                Test {
+                    name: "tests::it_is_slow",
+                    status: "pass",
+                    error: None,
+                    slow_warning: true,
+                    stdout: None,
+                    location: None,
+                    assertion: None,
+                    backtrace: vec![],
+                    failure_kind: None,
+                    duration: None,
+                    retries: 0
+                },
+                Test {
+                    name: "tests::it_is_fast",
+                    status: "pass",
+                    error: None,
+                    slow_warning: false,
+                    stdout: None,
+                    location: None,
+                    assertion: None,
+                    backtrace: vec![],
+                    failure_kind: None,
+                    duration: None,
+                    retries: 0
+                }
+            ],
+        );
+    }
+
+    #[test]
+    fn it_should_not_read_a_test_name_across_lines() {
+        let result = test_results(
+            &b"test tests::it_passes ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+     Running target/debug/integration_test-283604d1063344ba
+
+running 1 test
+test it_fails ... FAILED
+"
+                [..],
+        );
+
+        assert_eq!(
+            result,
+            IResult::Done(
+                &b"test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+     Running target/debug/integration_test-283604d1063344ba
+
+running 1 test
+test it_fails ... FAILED
+"[..],
+                vec![
+                    Test {
+                        name: "tests::it_passes",
+                        status: "pass",
+                        error: None,
+                        slow_warning: false,
+                        stdout: None,
+                        location: None,
+                        assertion: None,
+                        backtrace: vec![],
+                        failure_kind: None,
+                        duration: None,
+                        retries: 0
+                    }
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn it_should_parse_test_results() {
+        let result = test_results(
+            &b"test tests::it_should_parse_first_line ... ok
+test tests::it_should_parse_a_status_line ... ok
+test tests::it_should_parse_test_output ... ok
+test tests::it_should_parse_suite_line ... FAILED
+"
+                [..],
+        );
+
+        assert_done(
+            result,
+
+            vec![
+                Test {
+                    name: "tests::it_should_parse_first_line",
+                    status: "pass",
+                    error: None,
+                    slow_warning: false,
+                    stdout: None,
+                    location: None,
+                    assertion: None,
+                    backtrace: vec![],
+                    failure_kind: None,
+                    duration: None,
+                    retries: 0
+                },
+                Test {
+                    name: "tests::it_should_parse_a_status_line",
+                    status: "pass",
+                    error: None,
+                    slow_warning: false,
+                    stdout: None,
+                    location: None,
+                    assertion: None,
+                    backtrace: vec![],
+                    failure_kind: None,
+                    duration: None,
+                    retries: 0
+                },
+                Test {
+                    name: "tests::it_should_parse_test_output",
+                    status: "pass",
+                    error: None,
+                    slow_warning: false,
+                    stdout: None,
+                    location: None,
+                    assertion: None,
+                    backtrace: vec![],
+                    failure_kind: None,
+                    duration: None,
+                    retries: 0
+                },
+                Test {
+                    name: "tests::it_should_parse_suite_line",
+                    status: "fail",
+                    error: None,
+                    slow_warning: false,
+                    stdout: None,
+                    location: None,
+                    assertion: None,
+                    backtrace: vec![],
+                    failure_kind: None,
+                    duration: None,
+                    retries: 0
+                }
+              ],
+        );
+    }
+
+    #[test]
+    fn it_should_capture_digits() {
+        assert_done(digits(b"10"), 10);
+    }
+
+    #[test]
+    fn it_should_parse_a_suite_result() {
+        let result = suite_result(
+            &b"test result: FAILED. 3 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out"[..],
+        );
+
+        assert_done(
+            result,
+            SuiteResult {
+                state: "fail",
+                passed: 3,
+                failed: 1,
+                ignored: 0,
+                total: 4,
+                measured: 0,
+            },
+        );
+    }
+
+    #[test]
+    fn it_should_count_the_warnings_a_crate_printed() {
+        assert_done(
+            warnings_generated(&b"warning: `foo` (lib test) generated 1 warning (run `cargo fix --lib -p foo --tests` to apply 1 suggestion)"[..]),
+            ("foo", Some(1))
+        );
+        assert_done(
+            warnings_generated(&b"warning: `foo` (lib) generated 3 warnings (1 duplicate)"[..]),
+            ("foo", Some(2))
+        );
+    }
+
+    #[test]
+    fn it_should_parse_a_suite_result_with_a_duration() {
+        let result = suite_result(
+            &b"test result: ok. 3 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.52s
+"[..],
+        );
+
+        assert_done(
+            result,
+            SuiteResult {
+                state: "pass",
+                passed: 3,
+                failed: 0,
+                ignored: 0,
+                total: 3,
+                measured: 0,
+            },
+        );
+    }
+
+    #[test]
+    fn it_should_find_panic_locations() {
+        assert_eq!(
+            panic_location(
+                "thread 'tests::it_fails' (14066) panicked at src/lib.rs:6:48:
+assertion `left == right` failed"
+            ),
+            Some(SourceLocation {
+                file: "src/lib.rs".into(),
+                line: 6,
+                column: Some(48),
+            })
+        );
+
+        assert_eq!(
+            panic_location(
+                "thread 'tests::it_fails' panicked at 'assertion failed: `(left == right)`
+  left: `1`,
+ right: `2`: it's broken', C:\\foo\\src\\lib.rs:5:9"
+            ),
+            Some(SourceLocation {
+                file: "C:\\foo\\src\\lib.rs".into(),
+                line: 5,
+                column: Some(9),
+            })
+        );
+
+        assert_eq!(panic_location("Error: \"boom\""), None);
+    }
+
+    #[test]
+    fn it_should_parse_successful_test_output() {
+        let output = &b"    Finished debug [unoptimized + debuginfo] target(s) in 0.0 secs
+       Running target/debug/cargo_test_junit-83252957c74e106d
+
+running 2 tests
+test tests::it_should_match_failed ... ok
+test tests::it_should_parse_first_line ... ok
+
+
+  test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+  "
+            [..];
+
+        let result = parse_run(output);
+
+        assert_done(
+            result,
+            TestRun {
+                suites: vec![Suite {
+                name: "target/debug/cargo_test_junit-83252957c74e106d",
+                state: "pass",
+                tests: vec![
+                    Test {
+                        name: "tests::it_should_match_failed",
+                        status: "pass",
+                        error: None,
+                        slow_warning: false,
+                        stdout: None,
+                        location: None,
+                        assertion: None,
+                        backtrace: vec![],
+                        failure_kind: None,
+                        duration: None,
+                        retries: 0
+                    },
+                    Test {
+                        name: "tests::it_should_parse_first_line",
+                        status: "pass",
+                        error: None,
+                        slow_warning: false,
+                        stdout: None,
+                        location: None,
+                        assertion: None,
+                        backtrace: vec![],
+                        failure_kind: None,
+                        duration: None,
+                        retries: 0
+                    }
+                ],
+                passed: 2,
+                failed: 0,
+                ignored: 0,
+                measured: 0,
+                total: 2,
+                planned: 2,
+                missing: 0,
+                slow: vec![],
+                failure_names: vec![],
+                rerun_args: vec![],
+                warnings: vec![]
+                }],
+                build_failure: None,
+                warnings: vec![],
+                artifacts: HashMap::new()
+            },
+        );
+    }
+
+    #[test]
+    fn test_fail_line() {
+        let output = b"---- fail stdout ----";
+
+        assert_done(fail_line(output), "fail");
+    }
+
+    #[test]
+    fn test_failure() {
+        let output = b"---- fail stdout ----
+  thread 'fail' panicked at 'assertion failed: `(left == right)` (left: `1`, right: `2`)', tests/integration_test.rs:16
+note: Run with `RUST_BACKTRACE=1` for a backtrace.
+
+";
+        assert_done(
+            failure(output),
+            Failure {
+                name: "fail",
+                error: "thread 'fail' panicked at 'assertion failed: `(left == right)` \
+                                (left: `1`, right: `2`)', tests/integration_test.rs:16",
+                backtrace: vec![],
+                failure_kind: FailureKind::Panic,
+            },
+        );
+    }
+
+    #[test]
+    fn test_failures() {
+        let output = b"---- fail stdout ----
+          thread 'fail' panicked at 'assertion failed: `(left == right)` (left: `1`, right: `2`)', tests/integration_test.rs:16
+note: Run with `RUST_BACKTRACE=1` for a backtrace.
+
+        ---- fail2 stdout ----
+          thread 'fail2' panicked at 'assertion failed: `(left == right)` (left: `3`, right: `2`)', tests/integration_test.rs:22
+
+
+";
+
+        assert_done(
+            failures(output),
+            vec![
+                Failure {
+                    name: "fail",
+                    error: "thread 'fail' panicked at 'assertion failed: `(left == right)` (left: `1`, right: `2`)', tests/integration_test.rs:16",
+                    backtrace: vec![],
+                    failure_kind: FailureKind::Panic
+                },
+                Failure {
+                    name: "fail2",
+                    error: "thread 'fail2' panicked at 'assertion failed: `(left == right)` (left: `3`, right: `2`)', tests/integration_test.rs:22",
+                    backtrace: vec![],
+                    failure_kind: FailureKind::Panic
+                }
+            ],
+        );
+    }
+
+    #[test]
+    fn test_failure_with_backtrace() {
+        let output = b"---- tests::it_fails stdout ----
+thread 'tests::it_fails' panicked at src/lib.rs:6:9:
+boom
+stack backtrace:
+   0: __rustc::rust_begin_unwind
+             at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/std/src/panicking.rs:689:5
+   1: core::panicking::panic_fmt
+             at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/core/src/panicking.rs:80:14
+   2: demo::tests::it_fails
+             at ./src/lib.rs:6:9
+   3: demo::tests::it_fails::{{closure}}
+             at ./src/lib.rs:5:14
+   4: core::ops::function::FnOnce::call_once
+             at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/core/src/ops/function.rs:250:5
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.
+
+
+failures:
+";
+
+        let failure = match failure(output) {
+            IResult::Done(_, x) => x,
+            _ => panic!("BOOM!"),
+        };
+
+        assert_eq!(failure.error, "thread 'tests::it_fails' panicked at src/lib.rs:6:9:\nboom");
+        assert_eq!(failure.backtrace.len(), 5);
+        assert_eq!(
+            failure.backtrace[3],
+            Frame {
+                index: 3,
+                symbol: "demo::tests::it_fails::{{closure}}",
+                file: Some("./src/lib.rs"),
+                line: Some(5),
+            }
+        );
+    }
+
+    #[test]
+    fn test_should_panic_run() {
+        let output = b"    Finished `test` profile [unoptimized + debuginfo] target(s) in 0.01s
+     Running unittests src/lib.rs (target/debug/deps/demo-8348ca7a80742723)
+
+running 2 tests
+test tests::no_panic - should panic ... FAILED
+test tests::wrong_panic - should panic ... FAILED
+
+failures:
+
+---- tests::no_panic stdout ----
+note: test did not panic as expected at src/lib.rs:15:8
+---- tests::wrong_panic stdout ----
+
+thread 'tests::wrong_panic' (26661) panicked at src/lib.rs:19:24:
+foo
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+note: panic did not contain expected string
+      panic message: \"foo\"
+ expected substring: \"bar\"
+
+failures:
+    tests::no_panic
+    tests::wrong_panic
+
+test result: FAILED. 0 passed; 2 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+";
+
+        let run = parsed(parse_run(output));
+
+        let suite = &run.suites[0];
+
+        assert_eq!(suite.warnings, Vec::<String>::new());
+        assert_eq!(suite.tests[0].name, "tests::no_panic");
+        assert_eq!(suite.tests[0].failure_kind, Some(FailureKind::DidNotPanic));
+        assert_eq!(
+            suite.tests[0].location,
+            Some(SourceLocation {
+                file: "src/lib.rs".into(),
+                line: 15,
+                column: Some(8),
+            })
+        );
+        assert_eq!(suite.tests[1].name, "tests::wrong_panic");
+        assert_eq!(
+            suite.tests[1].failure_kind,
+            Some(FailureKind::WrongPanic {
+                expected: "bar",
+                actual: Some("foo"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_should_panic_run_printing_an_error() {
+        let output = b"     Running unittests src/lib.rs (target/debug/deps/demo-8348ca7a80742723)
+
+running 1 test
+test tests::retries - should panic ... FAILED
+
+failures:
+
+---- tests::retries stdout ----
+Error: retrying
+note: test did not panic as expected at src/lib.rs:15:8
+
+failures:
+    tests::retries
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+";
+
+        let run = parsed(parse_run(output));
+
+        let test = &run.suites[0].tests[0];
+
+        assert_eq!(test.failure_kind, Some(FailureKind::DidNotPanic));
+        assert_eq!(test.stdout, Some("Error: retrying"));
+        assert_eq!(test.error, Some("note: test did not panic as expected at src/lib.rs:15:8"));
+    }
+
+    #[test]
+    fn test_returned_error_run() {
+        let output = b"    Finished dev [unoptimized + debuginfo] target(s) in 0.0 secs
+     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 1 test
+test tests::connects ... FAILED
+
+failures:
+
+---- tests::connects stdout ----
+connecting
+Error: Os { code: 111, kind: ConnectionRefused, message: \"Connection refused\" }
+thread 'tests::connects' panicked at 'assertion failed: `(left == right)`
+  left: `1`,
+ right: `0`: the test returned a termination value with a non-zero status code (1) which indicates a failure', src/libtest/lib.rs:335:5
+note: Run with `RUST_BACKTRACE=1` for a backtrace.
+
+
+failures:
+    tests::connects
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+
+";
+
+        let run = parsed(parse_run(output));
+
+        let test = &run.suites[0].tests[0];
+
+        assert_eq!(test.stdout, Some("connecting"));
+        assert_eq!(
+            test.error,
+            Some("Error: Os { code: 111, kind: ConnectionRefused, message: \"Connection refused\" }")
+        );
+        assert_eq!(
+            test.failure_kind,
+            Some(FailureKind::ReturnedError(
+                "Os { code: 111, kind: ConnectionRefused, message: \"Connection refused\" }"
+            ))
+        );
+        assert_eq!(test.location, None);
+        assert_eq!(test.assertion, None);
+    }
+
+    #[test]
+    fn test_backtrace_run() {
+        let output = b"    Finished dev [unoptimized + debuginfo] target(s) in 0.0 secs
+     Running target/debug/deps/demo-0c3c5a3b0c7e1a2b
+
+running 1 test
+test tests::it_fails ... FAILED
+
+failures:
+
+---- tests::it_fails stdout ----
+thread 'tests::it_fails' panicked at src/lib.rs:6:9:
+boom
+stack backtrace:
+   0: __rustc::rust_begin_unwind
+             at /rustc/59807616e1fa2540724bfbac14d7976d7e4a3860/library/std/src/panicking.rs:689:5
+   1: demo::tests::it_fails
+             at ./src/lib.rs:6:9
+   2: test::run_test::{{closure}}
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.
+
+
+failures:
+    tests::it_fails
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+
+";
+
+        let run = parsed(parse_run(output));
+
+        let test = &run.suites[0].tests[0];
+
+        assert_eq!(test.error, Some("thread 'tests::it_fails' panicked at src/lib.rs:6:9:\nboom"));
+        assert_eq!(test.backtrace.len(), 3);
+        assert_eq!(
+            test.user_frames(),
+            vec![
+                &Frame {
+                    index: 1,
+                    symbol: "demo::tests::it_fails",
+                    file: Some("./src/lib.rs"),
+                    line: Some(6),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fail_run() {
+        let output = b"  Compiling blah v0.1.0 (file:blah)
+        Finished debug [unoptimized + debuginfo] target(s) in 0.32 secs
+        Running target/debug/deps/docker_command-be014e20fbd07382
+
+running 0 tests
+
+test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+        Running target/debug/integration_test-d4fc68dd5824cbb9
+
+running 3 tests
+test fail ... FAILED
+test fail2 ... FAILED
+test it_runs_a_command ... ok
+
+failures:
+
+---- fail stdout ----
+thread 'fail' panicked at 'assertion failed: `(left == right)` (left: `1`, right: `2`)', tests/integration_test.rs:16
+note: Run with `RUST_BACKTRACE=1` for a backtrace.
+
+---- fail2 stdout ----
+thread 'fail2' panicked at 'assertion failed: `(left == right)` (left: `3`, right: `2`)', tests/integration_test.rs:22
+
+
+failures:
+        fail
+        fail2
+
+test result: FAILED. 1 passed; 2 failed; 0 ignored; 0 measured; 0 filtered out
+
+error: test failed";
+
+        let x = parsed(parse_run(output));
+
+        assert_eq!(
+            x,
+            TestRun {
+                suites: vec![
+                    Suite {
+                        name: "target/debug/deps/docker_command-be014e20fbd07382",
+                        state: "pass",
+                        passed: 0,
+                        failed: 0,
+                        ignored: 0,
+                        measured: 0,
+                        total: 0,
+                        planned: 0,
+                        missing: 0,
+                        slow: vec![],
+                        failure_names: vec![],
+                        rerun_args: vec![],
+                        warnings: vec![],
+                        tests: vec![]
+                    },
+                    Suite {
+                        name: "target/debug/integration_test-d4fc68dd5824cbb9",
+                        state: "fail",
+                        passed: 1,
+                        failed: 2,
+                        ignored: 0,
+                        measured: 0,
+                        total: 3,
+                        planned: 3,
+                        missing: 0,
+                        slow: vec![],
+                        failure_names: vec!["fail", "fail2"],
+                        rerun_args: vec![],
+                        warnings: vec![],
+                        tests: vec![
+                            Test {
+                                name: "fail",
+                                status: "fail",
+                                error: Some("thread \'fail\' panicked at \'assertion failed: `(left == right)` (left: `1`, right: `2`)\', tests/integration_test.rs:16"),
+                                slow_warning: false,
+                                stdout: None,
+                                location: Some(SourceLocation {
+                                    file: "tests/integration_test.rs".into(),
+                                    line: 16,
+                                    column: None,
+                                }),
+                                assertion: Some(Assertion {
+                                    kind: "assert_eq",
+                                    left: "1",
+                                    right: "2",
+                                    message: None,
+                                }),
+                                backtrace: vec![],
+                                failure_kind: Some(FailureKind::Panic),
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "fail2",
+                                status: "fail",
+                                error: Some("thread \'fail2\' panicked at \'assertion failed: `(left == right)` (left: `3`, right: `2`)\', tests/integration_test.rs:22"),
+                                slow_warning: false,
+                                stdout: None,
+                                location: Some(SourceLocation {
+                                    file: "tests/integration_test.rs".into(),
+                                    line: 22,
+                                    column: None,
+                                }),
+                                assertion: Some(Assertion {
+                                    kind: "assert_eq",
+                                    left: "3",
+                                    right: "2",
+                                    message: None,
+                                }),
+                                backtrace: vec![],
+                                failure_kind: Some(FailureKind::Panic),
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "it_runs_a_command",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            }
+                        ]
+                    }
+                ],
+                build_failure: None,
+                warnings: vec![],
+                artifacts: HashMap::new()
+            }
         );
+    }
 
-        assert_done(result, ());
+    #[test]
+    fn test_crashed_run() {
+        let output = b"    Finished dev [unoptimized + debuginfo] target(s) in 0.51 secs
+     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 3 tests
+test tests::it_passes ... ok
+test tests::it_segfaults ... error: test failed, to rerun pass '--lib'
+
+Caused by:
+  process didn't exit successfully: `/foo/target/debug/deps/foo-5a7be5d1b9c8e0f6` (signal: 11, SIGSEGV: invalid memory reference)
+";
+
+        assert_done(
+            parse_run(output),
+            TestRun {
+                suites: vec![
+                    Suite {
+                        name: "target/debug/deps/foo-5a7be5d1b9c8e0f6",
+                        state: "fail",
+                        passed: 1,
+                        failed: 0,
+                        ignored: 0,
+                        measured: 0,
+                        total: 2,
+                        planned: 3,
+                        missing: 1,
+                        slow: vec![],
+                        failure_names: vec![],
+                        rerun_args: vec!["--lib"],
+                        warnings: vec![],
+                        tests: vec![
+                            Test {
+                                name: "tests::it_passes",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "tests::it_segfaults",
+                                status: "incomplete",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            }
+                        ]
+                    }
+                ],
+                build_failure: None,
+                warnings: vec![],
+                artifacts: HashMap::new()
+            },
+        );
     }
 
     #[test]
-    fn it_should_match_ok() {
-        assert_done(ok_or_failed(&b"ok"[..]), "pass");
+    fn test_timed_out_run() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 4 tests
+test tests::it_passes ... ok
+test tests::it_is_ignored ... ignored
+test tests::it_hangs ... ";
+
+        assert_done(
+            parse_run(output),
+            TestRun {
+                suites: vec![
+                    Suite {
+                        name: "target/debug/deps/foo-5a7be5d1b9c8e0f6",
+                        state: "fail",
+                        passed: 1,
+                        failed: 0,
+                        ignored: 1,
+                        measured: 0,
+                        total: 3,
+                        planned: 4,
+                        missing: 1,
+                        slow: vec![],
+                        failure_names: vec![],
+                        rerun_args: vec![],
+                        warnings: vec![],
+                        tests: vec![
+                            Test {
+                                name: "tests::it_passes",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "tests::it_is_ignored",
+                                status: "ignored",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "tests::it_hangs",
+                                status: "incomplete",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            }
+                        ]
+                    }
+                ],
+                build_failure: None,
+                warnings: vec![],
+                artifacts: HashMap::new()
+            },
+        );
     }
 
     #[test]
-    fn it_should_match_failed() {
-        assert_done(ok_or_failed(&b"FAILED"[..]), "fail");
+    fn test_killed_slow_run() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::it_passes ... ok
+test tests::it_hangs has been running for over 60 seconds
+";
+
+        assert_done(
+            parse_run(output),
+            TestRun {
+                suites: vec![
+                    Suite {
+                        name: "target/debug/deps/foo-5a7be5d1b9c8e0f6",
+                        state: "fail",
+                        passed: 1,
+                        failed: 0,
+                        ignored: 0,
+                        measured: 0,
+                        total: 2,
+                        planned: 2,
+                        missing: 0,
+                        slow: vec!["tests::it_hangs"],
+                        failure_names: vec![],
+                        rerun_args: vec![],
+                        warnings: vec![],
+                        tests: vec![
+                            Test {
+                                name: "tests::it_passes",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "tests::it_hangs",
+                                status: "incomplete",
+                                error: None,
+                                slow_warning: true,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            }
+                        ]
+                    }
+                ],
+                build_failure: None,
+                warnings: vec![],
+                artifacts: HashMap::new()
+            },
+        );
     }
 
     #[test]
-    fn it_should_parse_test_result() {
-        let result = test_result(&b"test it_runs_a_command ... ok"[..]);
+    fn test_run_continues_after_crashed_suite() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::it_passes ... ok
+thread 'tests::it_overflows' has overflowed its stack
+fatal runtime error: stack overflow
+error: test failed, to rerun pass '--lib'
+
+Caused by:
+  process didn't exit successfully: `/foo/target/debug/deps/foo-5a7be5d1b9c8e0f6` (signal: 6, SIGABRT: process abort signal)
+     Running target/debug/integration_test-283604d1063344ba
+
+running 1 test
+test it_runs_a_command ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+";
 
         assert_done(
-            result,
-            Test {
-                name: "it_runs_a_command",
-                status: "pass",
-                error: None,
+            parse_run(output),
+            TestRun {
+                suites: vec![
+                    Suite {
+                        name: "target/debug/deps/foo-5a7be5d1b9c8e0f6",
+                        state: "fail",
+                        passed: 1,
+                        failed: 0,
+                        ignored: 0,
+                        measured: 0,
+                        total: 1,
+                        planned: 2,
+                        missing: 1,
+                        slow: vec![],
+                        failure_names: vec![],
+                        rerun_args: vec!["--lib"],
+                        warnings: vec![],
+                        tests: vec![
+                            Test {
+                                name: "tests::it_passes",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            }
+                        ]
+                    },
+                    Suite {
+                        name: "target/debug/integration_test-283604d1063344ba",
+                        state: "pass",
+                        passed: 1,
+                        failed: 0,
+                        ignored: 0,
+                        measured: 0,
+                        total: 1,
+                        planned: 1,
+                        missing: 0,
+                        slow: vec![],
+                        failure_names: vec![],
+                        rerun_args: vec![],
+                        warnings: vec![],
+                        tests: vec![
+                            Test {
+                                name: "it_runs_a_command",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            }
+                        ]
+                    }
+                ],
+                build_failure: None,
+                warnings: vec![],
+                artifacts: HashMap::new()
             },
         );
     }
 
     #[test]
-    fn it_should_parse_test_results() {
-        let result = test_results(
-            &b"test tests::it_should_parse_first_line ... ok
-test tests::it_should_parse_a_status_line ... ok
-test tests::it_should_parse_test_output ... ok
-test tests::it_should_parse_suite_line ... FAILED
-"
-                [..],
+    fn test_unparsed_output_before_result() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::it_prints ... ok
+test tests::it_is_quiet ... ok
+
+successes:
+
+---- tests::it_prints stdout ----
+hello
+
+test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+";
+
+        let run = parsed(parse_run(output));
+
+        let suite = &run.suites[0];
+
+        assert_eq!(suite.state, "pass");
+        assert_eq!((suite.passed, suite.total, suite.missing), (2, 2, 0));
+        assert_eq!(
+            suite.warnings,
+            vec!["the output before `test result:` could not be parsed"]
         );
+    }
 
-        assert_done(
-            result,
+    #[test]
+    fn test_inconsistent_counts() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
 
-            vec![
-                Test {
-                    name: "tests::it_should_parse_first_line",
-                    status: "pass",
-                    error: None
-                },
-                Test {
-                    name: "tests::it_should_parse_a_status_line",
-                    status: "pass",
-                    error: None
-                },
-                Test {
-                    name: "tests::it_should_parse_test_output",
-                    status: "pass",
-                    error: None
-                },
-                Test {
-                    name: "tests::it_should_parse_suite_line",
-                    status: "fail",
-                    error: None
-                }
-              ],
+running 4 tests
+test tests::one ... ok
+test tests::two ... ok
+test tests::three ... ok
+
+test result: ok. 4 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+";
+
+        assert_done(
+            parse_run(output),
+            TestRun {
+                suites: vec![
+                    Suite {
+                        name: "target/debug/deps/foo-5a7be5d1b9c8e0f6",
+                        state: "pass",
+                        passed: 4,
+                        failed: 0,
+                        ignored: 0,
+                        measured: 0,
+                        total: 4,
+                        planned: 4,
+                        missing: 1,
+                        slow: vec![],
+                        failure_names: vec![],
+                        rerun_args: vec![],
+                        warnings: vec![
+                            "`running 4 tests` but 3 tests were listed".to_string(),
+                            "summary says 4 passed but 3 passed tests were listed".to_string()
+                        ],
+                        tests: vec![
+                            Test {
+                                name: "tests::one",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "tests::two",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "tests::three",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            }
+                        ]
+                    }
+                ],
+                build_failure: None,
+                warnings: vec![],
+                artifacts: HashMap::new()
+            },
         );
     }
 
     #[test]
-    fn it_should_capture_digits() {
-        assert_done(digits(b"10"), 10);
+    fn test_show_output_run() {
+        let output = b"     Running unittests src/lib.rs (target/debug/deps/foo-5a7be5d1b9c8e0f6)
+
+running 3 tests
+test tests::it_fails ... FAILED
+test tests::it_prints ... ok
+test tests::it_is_quiet ... ok
+
+successes:
+
+---- tests::it_prints stdout ----
+hello
+world
+
+
+successes:
+    tests::it_prints
+    tests::it_is_quiet
+
+failures:
+
+---- tests::it_fails stdout ----
+about to fail
+
+thread 'tests::it_fails' panicked at src/lib.rs:6:9:
+assertion `left == right` failed
+  left: 1
+ right: 2
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    tests::it_fails
+
+test result: FAILED. 2 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+";
+
+        assert_done(
+            parse_run(output),
+            TestRun {
+                suites: vec![
+                    Suite {
+                        name: "unittests src/lib.rs (target/debug/deps/foo-5a7be5d1b9c8e0f6)",
+                        state: "fail",
+                        passed: 2,
+                        failed: 1,
+                        ignored: 0,
+                        measured: 0,
+                        total: 3,
+                        planned: 3,
+                        missing: 0,
+                        slow: vec![],
+                        failure_names: vec!["tests::it_fails"],
+                        rerun_args: vec![],
+                        warnings: vec![],
+                        tests: vec![
+                            Test {
+                                name: "tests::it_fails",
+                                status: "fail",
+                                error: Some(
+                                    "thread 'tests::it_fails' panicked at src/lib.rs:6:9:
+assertion `left == right` failed
+  left: 1
+ right: 2"
+                                ),
+                                slow_warning: false,
+                                stdout: Some("about to fail"),
+                                location: Some(SourceLocation {
+                                    file: "src/lib.rs".into(),
+                                    line: 6,
+                                    column: Some(9),
+                                }),
+                                assertion: Some(Assertion {
+                                    kind: "assert_eq",
+                                    left: "1",
+                                    right: "2",
+                                    message: None,
+                                }),
+                                backtrace: vec![],
+                                failure_kind: Some(FailureKind::Panic),
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "tests::it_prints",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: Some("hello\nworld"),
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "tests::it_is_quiet",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            }
+                        ]
+                    }
+                ],
+                build_failure: None,
+                warnings: vec![],
+                artifacts: HashMap::new()
+            },
+        );
     }
 
     #[test]
-    fn it_should_parse_a_suite_result() {
-        let result = suite_result(
-            &b"test result: FAILED. 3 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out"[..],
+    fn test_nocapture_run() {
+        let output = b"     Running unittests src/lib.rs (target/debug/deps/foo-5a7be5d1b9c8e0f6)
+
+running 4 tests
+from another thread
+Running migrations
+test tests::it_fails ... about to fail
+
+thread 'tests::it_fails' panicked at src/lib.rs:6:9:
+assertion `left == right` failed
+  left: 1
+ right: 2
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+FAILED
+test tests::it_prints ... hello
+Running migrations
+world
+ok
+test tests::it_is_quiet ... ok
+test tests::it_is_ignored ... ignored
+
+failures:
+
+failures:
+    tests::it_fails
+
+test result: FAILED. 2 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+";
+
+        assert_done(
+            parse_run(output),
+            TestRun {
+                suites: vec![
+                    Suite {
+                        name: "unittests src/lib.rs (target/debug/deps/foo-5a7be5d1b9c8e0f6)",
+                        state: "fail",
+                        passed: 2,
+                        failed: 1,
+                        ignored: 1,
+                        measured: 0,
+                        total: 4,
+                        planned: 4,
+                        missing: 0,
+                        slow: vec![],
+                        failure_names: vec!["tests::it_fails"],
+                        rerun_args: vec![],
+                        warnings: vec![],
+                        tests: vec![
+                            Test {
+                                name: "tests::it_fails",
+                                status: "fail",
+                                error: Some(
+                                    "thread 'tests::it_fails' panicked at src/lib.rs:6:9:
+assertion `left == right` failed
+  left: 1
+ right: 2"
+                                ),
+                                slow_warning: false,
+                                stdout: Some("about to fail"),
+                                location: Some(SourceLocation {
+                                    file: "src/lib.rs".into(),
+                                    line: 6,
+                                    column: Some(9),
+                                }),
+                                assertion: Some(Assertion {
+                                    kind: "assert_eq",
+                                    left: "1",
+                                    right: "2",
+                                    message: None,
+                                }),
+                                backtrace: vec![],
+                                failure_kind: Some(FailureKind::Panic),
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "tests::it_prints",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: Some("hello\nRunning migrations\nworld"),
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "tests::it_is_quiet",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "tests::it_is_ignored",
+                                status: "ignored",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            }
+                        ]
+                    }
+                ],
+                build_failure: None,
+                warnings: vec![],
+                artifacts: HashMap::new()
+            },
         );
+    }
+
+    #[test]
+    fn test_failure_names_disagree_with_results() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::one ... FAILED
+test tests::two ... ok
+
+failures:
+
+---- tests::one stdout ----
+thread 'tests::one' panicked at 'boom', src/lib.rs:10
+
+
+failures:
+    tests::two
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+";
 
         assert_done(
-            result,
-            SuiteResult {
-                state: "fail",
-                passed: 3,
-                failed: 1,
-                ignored: 0,
-                total: 4,
-                measured: 0,
+            parse_run(output),
+            TestRun {
+                suites: vec![
+                    Suite {
+                        name: "target/debug/deps/foo-5a7be5d1b9c8e0f6",
+                        state: "fail",
+                        passed: 1,
+                        failed: 1,
+                        ignored: 0,
+                        measured: 0,
+                        total: 2,
+                        planned: 2,
+                        missing: 0,
+                        slow: vec![],
+                        failure_names: vec!["tests::two"],
+                        rerun_args: vec![],
+                        warnings: vec![
+                            "`tests::one` failed but is not listed under `failures:`".to_string(),
+                            "`tests::two` is listed under `failures:` but was not marked FAILED"
+                                .to_string()
+                        ],
+                        tests: vec![
+                            Test {
+                                name: "tests::one",
+                                status: "fail",
+                                error: Some("thread 'tests::one' panicked at 'boom', src/lib.rs:10"),
+                                slow_warning: false,
+                                stdout: None,
+                                location: Some(SourceLocation {
+                                    file: "src/lib.rs".into(),
+                                    line: 10,
+                                    column: None,
+                                }),
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: Some(FailureKind::Panic),
+                                duration: None,
+                                retries: 0
+                            },
+                            Test {
+                                name: "tests::two",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            }
+                        ]
+                    }
+                ],
+                build_failure: None,
+                warnings: vec![],
+                artifacts: HashMap::new()
             },
         );
     }
 
     #[test]
-    fn it_should_parse_successful_test_output() {
-        let output = &b"    Finished debug [unoptimized + debuginfo] target(s) in 0.0 secs
-       Running target/debug/cargo_test_junit-83252957c74e106d
+    fn test_bench_run() {
+        let output = b"    Finished `bench` profile [optimized] target(s) in 0.02s
+     Running benches/bench.rs (target/release/deps/bench-1a2b3c4d5e6f7a8b)
+
+running 2 tests
+test bench_add ... bench:       1,234 ns/iter (+/- 5)
+test tests::it_works ... ignored
+
+test result: ok. 0 passed; 0 failed; 1 ignored; 1 measured; 0 filtered out; finished in 0.52s
+
+";
+
+        let run = parsed(parse_run(output));
+
+        let suite = &run.suites[0];
+
+        assert_eq!(suite.warnings, Vec::<String>::new());
+        assert_eq!((suite.state, suite.measured, suite.ignored, suite.missing), ("pass", 1, 1, 0));
+        assert_eq!(suite.tests[0].name, "bench_add");
+        assert_eq!(suite.tests[0].status, "measured");
+        assert_eq!(suite.rerun_command(), None);
+    }
+
+    #[test]
+    fn test_crlf_fail_run() {
+        let output = b"     Running target\\debug\\deps\\foo-5a7be5d1b9c8e0f6.exe\r
+\r
+running 1 test\r
+test tests::it_fails ... FAILED\r
+\r
+failures:\r
+\r
+---- tests::it_fails stdout ----\r
+thread 'tests::it_fails' panicked at src\\lib.rs:6:9:\r
+boom\r
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace\r
+\r
+\r
+failures:\r
+    tests::it_fails\r
+\r
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s\r
+\r
+";
+
+        let normalized = normalize(output);
+
+        let run = parsed(cargo_test_result_parser(&normalized));
+
+        let test = &run.suites[0].tests[0];
+
+        assert_eq!(test.status, "fail");
+        assert_eq!(test.error, Some("thread 'tests::it_fails' panicked at src\\lib.rs:6:9:\nboom"));
+        assert_eq!(run.suites[0].warnings, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_run_with_durations_in_summaries() {
+        let output = b"     Running unittests src/lib.rs (target/debug/deps/foo-5a7be5d1b9c8e0f6)
+
+running 1 test
+test tests::it_passes ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+     Running tests/integration.rs (target/debug/deps/integration-283604d1063344ba)
+
+running 2 tests
+test it_runs_a_command ... ok
+test it_is_ignored ... ignored
+
+test result: ok. 1 passed; 0 failed; 1 ignored; 0 measured; 0 filtered out; finished in 1.52s
+
+";
+
+        let run = parsed(parse_run(output));
+
+        let suites: Vec<(&str, &str, i64, i64)> = run.suites
+            .iter()
+            .map(|x| (x.name, x.state, x.total, x.planned))
+            .collect();
+
+        assert_eq!(
+            suites,
+            vec![
+                ("unittests src/lib.rs (target/debug/deps/foo-5a7be5d1b9c8e0f6)", "pass", 1, 1),
+                ("tests/integration.rs (target/debug/deps/integration-283604d1063344ba)", "pass", 2, 2),
+            ]
+        );
+
+        let tests: Vec<&str> = run.suites[1].tests.iter().map(|x| x.name).collect();
+        assert_eq!(tests, vec!["it_runs_a_command", "it_is_ignored"]);
+        assert!(run.suites.iter().all(|x| x.warnings.is_empty()));
+    }
+
+    #[test]
+    fn test_failure_names_after_garbled_output() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::it_passes ... ok
+test tests::it_fails ... FAILED
+
+failures:
+
+background tick---- tests::it_fails stdout ----
+thread 'tests::it_fails' panicked at 'boom', src/lib.rs:10
+
 
-running 2 tests
-test tests::it_should_match_failed ... ok
-test tests::it_should_parse_first_line ... ok
+failures:
+    tests::it_fails
 
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+";
 
-  test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
-  "
-            [..];
+        let run = parsed(parse_run(output));
 
-        let result = cargo_test_result_parser(output);
+        let suite = &run.suites[0];
 
-        assert_done(
-            result,
-            vec![Suite {
-            name: "target/debug/cargo_test_junit-83252957c74e106d",
-            state: "pass",
-            tests: vec![
-                Test {
-                    name: "tests::it_should_match_failed",
-                    status: "pass",
-                    error: None
-                },
-                Test {
-                    name: "tests::it_should_parse_first_line",
-                    status: "pass",
-                    error: None
-                }
-            ],
-            passed: 2,
-            failed: 0,
-            ignored: 0,
-            measured: 0,
-            total: 2
-        }],
-        );
+        assert_eq!(suite.state, "fail");
+        assert_eq!(suite.failure_names, vec!["tests::it_fails"]);
+        assert_eq!(suite.warnings, Vec::<String>::new());
+        assert_eq!(suite.tests[1].status, "fail");
     }
 
     #[test]
-    fn test_fail_line() {
-        let output = b"---- fail stdout ----";
+    fn test_rerun_hints() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
 
-        assert_done(fail_line(output), "fail");
-    }
+running 1 test
+test tests::it_fails ... FAILED
 
-    #[test]
-    fn test_failure() {
-        let output = b"---- fail stdout ----
-  thread 'fail' panicked at 'assertion failed: `(left == right)` (left: `1`, right: `2`)', tests/integration_test.rs:16
-note: Run with `RUST_BACKTRACE=1` for a backtrace.
+failures:
 
+---- tests::it_fails stdout ----
+thread 'tests::it_fails' panicked at 'boom', src/lib.rs:10
+
+
+failures:
+    tests::it_fails
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+
+error: test failed, to rerun pass '-p foo --lib'
+     Running target/debug/integration_test-283604d1063344ba
+
+running 2 tests
+test it_runs_a_command ... ok
+test it_crashes ... error: test failed, to rerun pass `--test integration_test`
 ";
-        assert_done(
-            failure(output),
-            Failure {
-                name: "fail",
-                error: "thread 'fail' panicked at 'assertion failed: `(left == right)` \
-                                (left: `1`, right: `2`)', tests/integration_test.rs:16",
-            },
+
+        let run = parsed(parse_run(output));
+
+        assert_eq!(run.suites.len(), 2);
+        assert_eq!(run.suites[0].rerun_args, vec!["-p", "foo", "--lib"]);
+        assert_eq!(run.suites[1].rerun_args, vec!["--test", "integration_test"]);
+
+        assert_eq!(
+            run.suites[0].rerun_command(),
+            Some(
+                vec!["cargo", "test", "-p", "foo", "--lib", "--", "tests::it_fails", "--exact"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            )
+        );
+        assert_eq!(
+            run.suites[1].rerun_command(),
+            Some(
+                vec!["cargo", "test", "--test", "integration_test", "--", "it_crashes", "--exact"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            )
         );
     }
 
     #[test]
-    fn test_failures() {
-        let output = b"---- fail stdout ----
-          thread 'fail' panicked at 'assertion failed: `(left == right)` (left: `1`, right: `2`)', tests/integration_test.rs:16
-note: Run with `RUST_BACKTRACE=1` for a backtrace.
+    fn test_rerun_hints_after_no_fail_fast() {
+        let output = b"     Running unittests src/lib.rs (target/debug/deps/foo-5a7be5d1b9c8e0f6)
 
-        ---- fail2 stdout ----
-          thread 'fail2' panicked at 'assertion failed: `(left == right)` (left: `3`, right: `2`)', tests/integration_test.rs:22
+running 1 test
+test tests::it_fails ... FAILED
+
+failures:
+
+failures:
+    tests::it_fails
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+     Running tests/passing.rs (target/debug/deps/passing-1b6d5c1e2c3a4f5e)
+
+running 1 test
+test it_passes ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+     Running tests/failing.rs (target/debug/deps/failing-283604d1063344ba)
+
+running 1 test
+test it_fails ... FAILED
+
+failures:
+
+failures:
+    it_fails
 
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
 
+error: 2 targets failed:
+    `--lib`
+    `--test failing`
 ";
 
-        assert_done(
-            failures(output),
-            vec![
-                Failure {
-                    name: "fail",
-                    error: "thread 'fail' panicked at 'assertion failed: `(left == right)` (left: `1`, right: `2`)', tests/integration_test.rs:16"
-                },
-                Failure {
-                    name: "fail2",
-                    error: "thread 'fail2' panicked at 'assertion failed: `(left == right)` (left: `3`, right: `2`)', tests/integration_test.rs:22"
-                }
-            ],
-        );
+        let run = parsed(parse_run(output));
+
+        let args: Vec<Vec<&str>> = run.suites.iter().map(|x| x.rerun_args.clone()).collect();
+
+        assert_eq!(args, vec![vec!["--lib"], vec![], vec!["--test", "failing"]]);
+        assert_eq!(run.suites[1].rerun_command(), None);
     }
 
     #[test]
-    fn test_fail_run() {
-        let output = b"  Compiling blah v0.1.0 (file:blah)
-        Finished debug [unoptimized + debuginfo] target(s) in 0.32 secs
-        Running target/debug/deps/docker_command-be014e20fbd07382
+    fn test_rerun_hints_for_the_targets_they_name() {
+        let output = b"     Running unittests src/lib.rs (target/debug/deps/foo-5a7be5d1b9c8e0f6)
 
-running 0 tests
+running 1 test
+test tests::it_fails ... FAILED
 
-test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+failures:
 
-        Running target/debug/integration_test-d4fc68dd5824cbb9
+failures:
+    tests::it_fails
 
-running 3 tests
-test fail ... FAILED
-test fail2 ... FAILED
-test it_runs_a_command ... ok
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+     Running tests/failing.rs (target/debug/deps/failing-283604d1063344ba)
+
+running 1 test
+test it_fails ... FAILED
 
 failures:
 
----- fail stdout ----
-thread 'fail' panicked at 'assertion failed: `(left == right)` (left: `1`, right: `2`)', tests/integration_test.rs:16
-note: Run with `RUST_BACKTRACE=1` for a backtrace.
+failures:
+    it_fails
 
----- fail2 stdout ----
-thread 'fail2' panicked at 'assertion failed: `(left == right)` (left: `3`, right: `2`)', tests/integration_test.rs:22
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+   Doc-tests foo
 
+running 1 test
+test src/lib.rs - add (line 3) ... FAILED
 
 failures:
-        fail
-        fail2
 
-test result: FAILED. 1 passed; 2 failed; 0 ignored; 0 measured; 0 filtered out
+failures:
+    src/lib.rs - add (line 3)
 
-error: test failed";
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
 
-        let x = match cargo_test_result_parser(output) {
-            IResult::Done(_, x) => x,
-            _ => panic!("BOOM!"),
-        };
+error: 2 targets failed:
+    `--doc`
+    `--test failing`
+";
 
-        assert_eq!(
-            x,
-            vec![
-                Suite {
-                    name: "target/debug/deps/docker_command-be014e20fbd07382",
-                    state: "pass",
-                    passed: 0,
-                    failed: 0,
-                    ignored: 0,
-                    measured: 0,
-                    total: 0,
-                    tests: vec![]
-                },
-                Suite {
-                    name: "target/debug/integration_test-d4fc68dd5824cbb9",
-                    state: "fail",
-                    passed: 1,
-                    failed: 2,
-                    ignored: 0,
-                    measured: 0,
-                    total: 3,
-                    tests: vec![
-                        Test {
-                            name: "fail",
-                            status: "fail",
-                            error: Some("thread \'fail\' panicked at \'assertion failed: `(left == right)` (left: `1`, right: `2`)\', tests/integration_test.rs:16")
-                        },
-                        Test {
-                            name: "fail2",
-                            status: "fail",
-                            error: Some("thread \'fail2\' panicked at \'assertion failed: `(left == right)` (left: `3`, right: `2`)\', tests/integration_test.rs:22")
-                        },
-                        Test {
-                            name: "it_runs_a_command",
-                            status: "pass",
-                            error: None
-                        }
-                    ]
-                }
-            ]
-        );
+        let run = parsed(parse_run(output));
+
+        let args: Vec<Vec<&str>> = run.suites.iter().map(|x| x.rerun_args.clone()).collect();
+
+        assert_eq!(args, vec![vec![], vec!["--test", "failing"], vec!["--doc"]]);
     }
 
     #[test]
@@ -707,45 +3703,76 @@ running 0 tests
 test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out";
 
         assert_done(
-            cargo_test_result_parser(output),
-            vec![
-                      Suite {
-                          name: "target/debug/deps/foo-5a7be5d1b9c8e0f6",
-                          state: "pass",
-                          passed: 0,
-                          failed: 0,
-                          ignored: 0,
-                          measured: 0,
-                          total: 0,
-                          tests: vec![]
-                      },
-                      Suite {
-                          name: "target/debug/integration_test-283604d1063344ba",
-                          state: "pass",
-                          passed: 1,
-                          failed: 0,
-                          ignored: 0,
-                          measured: 0,
-                          total: 1,
-                          tests: vec![
-                              Test {
-                                  name: "it_runs_a_command",
-                                  status: "pass",
-                                  error: None
-                              }
-                          ]
-                      },
-                      Suite {
-                          name: "foo",
-                          state: "pass",
-                          passed: 0,
-                          failed: 0,
-                          ignored: 0,
-                          measured: 0,
-                          total: 0,
-                          tests: vec![]
-                      }
-                  ],
+            parse_run(output),
+            TestRun {
+                suites: vec![
+                          Suite {
+                              name: "target/debug/deps/foo-5a7be5d1b9c8e0f6",
+                              state: "pass",
+                              passed: 0,
+                              failed: 0,
+                              ignored: 0,
+                              measured: 0,
+                              total: 0,
+                              planned: 0,
+                              missing: 0,
+                              slow: vec![],
+                              failure_names: vec![],
+                              rerun_args: vec![],
+                              warnings: vec![],
+                              tests: vec![]
+                          },
+                          Suite {
+                              name: "target/debug/integration_test-283604d1063344ba",
+                              state: "pass",
+                              passed: 1,
+                              failed: 0,
+                              ignored: 0,
+                              measured: 0,
+                              total: 1,
+                              planned: 1,
+                              missing: 0,
+                              slow: vec![],
+                              failure_names: vec![],
+                              rerun_args: vec![],
+                              warnings: vec![],
+                              tests: vec![
+                                  Test {
+                                      name: "it_runs_a_command",
+                                      status: "pass",
+                                      error: None,
+                                      slow_warning: false,
+                                      stdout: None,
+                                      location: None,
+                                      assertion: None,
+                                      backtrace: vec![],
+                                      failure_kind: None,
+                                      duration: None,
+                                      retries: 0
+                                  }
+                              ]
+                          },
+                          Suite {
+                              name: "foo",
+                              state: "pass",
+                              passed: 0,
+                              failed: 0,
+                              ignored: 0,
+                              measured: 0,
+                              total: 0,
+                              planned: 0,
+                              missing: 0,
+                              slow: vec![],
+                              failure_names: vec![],
+                              rerun_args: vec![],
+                              warnings: vec![],
+                              tests: vec![]
+                          }
+                      ],
+                build_failure: None,
+                warnings: vec![],
+                artifacts: HashMap::new()
+            },
         );
     }
 
@@ -856,47 +3883,272 @@ test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
     ";
 
         assert_done(
-            cargo_test_result_parser(output),
+            parse_run(output),
+            TestRun {
+                suites: vec![
+                              Suite {
+                                  name: "target/debug/deps/libzfs_sys-a797c24cd4b4a7ea",
+                                  state: "pass",
+                                  passed: 3,
+                                  failed: 0,
+                                  ignored: 0,
+                                  measured: 0,
+                                  total: 3,
+                                  planned: 3,
+                                  missing: 0,
+                                  slow: vec![],
+                                  failure_names: vec![],
+                                  rerun_args: vec![],
+                                  warnings: vec![],
+                                  tests: vec![
+                                    Test {
+                                      name: "bindgen_test_layout_zpool_handle",
+                                      status: "pass",
+                                      error: None,
+                                      slow_warning: false,
+                                      stdout: None,
+                                      location: None,
+                                      assertion: None,
+                                      backtrace: vec![],
+                                      failure_kind: None,
+                                      duration: None,
+                                      retries: 0
+                                    },
+                                    Test {
+                                      name: "tests::open_close_handle",
+                                      status: "pass",
+                                      error: None,
+                                      slow_warning: false,
+                                      stdout: None,
+                                      location: None,
+                                      assertion: None,
+                                      backtrace: vec![],
+                                      failure_kind: None,
+                                      duration: None,
+                                      retries: 0
+                                    },
+                                    Test {
+                                      name: "tests::pool_search_import_list_export",
+                                      status: "pass",
+                                      error: None,
+                                      slow_warning: false,
+                                      stdout: None,
+                                      location: None,
+                                      assertion: None,
+                                      backtrace: vec![],
+                                      failure_kind: None,
+                                      duration: None,
+                                      retries: 0
+                                    }
+                                  ]
+                              },
+                              Suite {
+                                  name: "libzfs-sys",
+                                  state: "pass",
+                                  passed: 0,
+                                  failed: 0,
+                                  ignored: 0,
+                                  measured: 0,
+                                  total: 0,
+                                  planned: 0,
+                                  missing: 0,
+                                  slow: vec![],
+                                  failure_names: vec![],
+                                  rerun_args: vec![],
+                                  warnings: vec![],
+                                  tests: vec![]
+                              }
+                          ],
+                build_failure: None,
+                warnings: vec![],
+                artifacts: HashMap::new()
+            },
+        );
+
+    }
+
+    #[test]
+    fn test_run_with_warnings() {
+        let output = b"   Compiling foo v0.1.0 (/foo)
+warning: unused variable: `x`
+ --> src/lib.rs:2:9
+  |
+2 |     let x = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+warning: unused manifest key: package.foo
+warning: `foo` (lib test) generated 1 warning (run `cargo fix --lib -p foo --tests` to apply 1 suggestion)
+    Finished `test` profile [unoptimized + debuginfo] target(s) in 0.25s
+     Running unittests src/lib.rs (target/debug/deps/foo-5a7be5d1b9c8e0f6)
+
+running 1 test
+test it_runs_a_command ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+";
+
+        assert_done(
+            parse_run(output),
+            TestRun {
+                suites: vec![
+                    Suite {
+                        name: "unittests src/lib.rs (target/debug/deps/foo-5a7be5d1b9c8e0f6)",
+                        state: "pass",
+                        passed: 1,
+                        failed: 0,
+                        ignored: 0,
+                        measured: 0,
+                        total: 1,
+                        planned: 1,
+                        missing: 0,
+                        slow: vec![],
+                        failure_names: vec![],
+                        rerun_args: vec![],
+                        warnings: vec![],
+                        tests: vec![
+                            Test {
+                                name: "it_runs_a_command",
+                                status: "pass",
+                                error: None,
+                                slow_warning: false,
+                                stdout: None,
+                                location: None,
+                                assertion: None,
+                                backtrace: vec![],
+                                failure_kind: None,
+                                duration: None,
+                                retries: 0
+                            }
+                        ]
+                    }
+                ],
+                build_failure: None,
+                warnings: vec![
+                    Diagnostic {
+                        code: Some("unused_variables".into()),
+                        message: "unused variable: `x`".into(),
+                        location: Some(SourceLocation {
+                            file: "src/lib.rs".into(),
+                            line: 2,
+                            column: Some(9),
+                        }),
+                        notes: vec!["note: `#[warn(unused_variables)]` on by default".into()],
+                        crate_name: Some("foo".into()),
+                    },
+                    Diagnostic {
+                        code: None,
+                        message: "unused manifest key: package.foo".into(),
+                        location: None,
+                        notes: vec![],
+                        crate_name: None,
+                    },
+                ],
+                artifacts: HashMap::new(),
+            },
+        );
+    }
+
+    #[test]
+    fn test_json_message_format_run() {
+        let output = br#"   Compiling foo v0.1.0 (/foo)
+{"reason":"compiler-message","package_id":"path+file:///foo#0.1.0","manifest_path":"/foo/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"foo","src_path":"/foo/src/lib.rs","edition":"2021","doc":true,"doctest":true,"test":true},"message":{"rendered":"warning: unused variable: `x`\n","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"note","message":"`#[warn(unused_variables)]` on by default","rendered":null,"spans":[]}],"code":{"code":"unused_variables","explanation":null},"level":"warning","message":"unused variable: `x`","spans":[{"byte_end":30,"byte_start":29,"column_end":10,"column_start":9,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}]}}
+{"reason":"compiler-message","package_id":"path+file:///foo#0.1.0","manifest_path":"/foo/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"foo","src_path":"/foo/src/lib.rs","edition":"2021","doc":true,"doctest":true,"test":true},"message":{"rendered":"warning: 1 warning emitted\n\n","$message_type":"diagnostic","children":[],"code":null,"level":"warning","message":"1 warning emitted","spans":[]}}
+{"reason":"compiler-artifact","package_id":"path+file:///foo#0.1.0","manifest_path":"/foo/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"foo","src_path":"/foo/src/lib.rs","edition":"2021","doc":true,"doctest":true,"test":true},"profile":{"opt_level":"0","debuginfo":2,"debug_assertions":true,"overflow_checks":true,"test":true},"features":[],"filenames":["/foo/target/debug/deps/foo-5a7be5d1b9c8e0f6"],"executable":"/foo/target/debug/deps/foo-5a7be5d1b9c8e0f6","fresh":false}
+{"reason":"build-finished","success":true}
+    Finished `test` profile [unoptimized + debuginfo] target(s) in 0.25s
+     Running unittests src/lib.rs (target/debug/deps/foo-5a7be5d1b9c8e0f6)
+
+running 1 test
+test it_runs_a_command ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+"#;
+
+        let run = parsed(parse_run(output));
+
+        assert_eq!(
+            run.warnings,
             vec![
-                          Suite {
-                              name: "target/debug/deps/libzfs_sys-a797c24cd4b4a7ea",
-                              state: "pass",
-                              passed: 3,
-                              failed: 0,
-                              ignored: 0,
-                              measured: 0,
-                              total: 3,
-                              tests: vec![
-                                Test {
-                                  name: "bindgen_test_layout_zpool_handle",
-                                  status: "pass",
-                                  error: None
-                                },
-                                Test {
-                                  name: "tests::open_close_handle",
-                                  status: "pass",
-                                  error: None
-                                },
-                                Test {
-                                  name: "tests::pool_search_import_list_export",
-                                  status: "pass",
-                                  error: None
-                                }
-                              ]
-                          },
-                          Suite {
-                              name: "libzfs-sys",
-                              state: "pass",
-                              passed: 0,
-                              failed: 0,
-                              ignored: 0,
-                              measured: 0,
-                              total: 0,
-                              tests: vec![]
-                          }
-                      ],
+                Diagnostic {
+                    code: Some("unused_variables".into()),
+                    message: "unused variable: `x`".into(),
+                    location: Some(SourceLocation {
+                        file: "src/lib.rs".into(),
+                        line: 2,
+                        column: Some(9),
+                    }),
+                    notes: vec!["note: `#[warn(unused_variables)]` on by default".into()],
+                    crate_name: Some("foo".into()),
+                },
+            ]
+        );
+
+        assert_eq!(
+            run.artifacts,
+            vec![
+                (
+                    "/foo/target/debug/deps/foo-5a7be5d1b9c8e0f6".to_string(),
+                    "path+file:///foo#0.1.0".to_string()
+                ),
+            ].into_iter().collect()
+        );
+
+        assert_eq!(run.build_failure, None);
+        assert_eq!(run.suites.len(), 1);
+        assert_eq!(run.package_id(&run.suites[0]), Some("path+file:///foo#0.1.0"));
+
+        let mut run = run;
+        run.artifacts.insert(
+            "/other/target/debug/deps/foo-5a7be5d1b9c8e0f6".to_string(),
+            "path+file:///other#0.1.0".to_string(),
         );
+        run.artifacts.insert("/foo/target/debug/deps/barfoo".to_string(), "path+file:///bar#0.1.0".to_string());
 
+        assert_eq!(run.package_id(&run.suites[0]), Some("path+file:///foo#0.1.0"));
+
+        run.suites[0].name = "foo";
+        assert_eq!(run.package_id(&run.suites[0]), None);
+    }
+
+    #[test]
+    fn test_json_message_format_compile_fail() {
+        let output = br#"   Compiling libzfs v0.1.0 (file:///vagrant/libzfs)
+{"reason":"compiler-message","package_id":"libzfs 0.1.0 (path+file:///vagrant/libzfs)","target":{"kind":["lib"],"name":"libzfs","src_path":"/vagrant/libzfs/src/lib.rs"},"message":{"children":[{"children":[],"code":null,"level":"note","message":"an implementation of `std::cmp::PartialEq` might be missing","rendered":null,"spans":[]}],"code":{"code":"E0369","explanation":"..."},"level":"error","message":"binary operation `==` cannot be applied to type `std::result::Result<nvpair::NvData, std::io::Error>`","rendered":"error[E0369]: ...","spans":[{"column_start":9,"file_name":"libzfs/src/lib.rs","is_primary":true,"line_start":134}]}}
+{"reason":"compiler-message","package_id":"libzfs 0.1.0 (path+file:///vagrant/libzfs)","target":{"kind":["lib"],"name":"libzfs","src_path":"/vagrant/libzfs/src/lib.rs"},"message":{"children":[],"code":null,"level":"error","message":"aborting due to 1 previous error","rendered":"error: aborting due to 1 previous error\n\n","spans":[]}}
+{"reason":"build-finished","success":false}
+"#;
+
+        assert_done(
+            parse_run(output),
+            TestRun {
+                suites: vec![],
+                build_failure: Some(BuildFailure {
+                    crate_name: Some("libzfs".into()),
+                    diagnostics: vec![
+                        Diagnostic {
+                            code: Some("E0369".into()),
+                            message: "binary operation `==` cannot be applied to type \
+                                      `std::result::Result<nvpair::NvData, std::io::Error>`"
+                                .into(),
+                            location: Some(SourceLocation {
+                                file: "libzfs/src/lib.rs".into(),
+                                line: 134,
+                                column: Some(9),
+                            }),
+                            notes: vec![
+                                "note: an implementation of `std::cmp::PartialEq` might be missing"
+                                    .into(),
+                            ],
+                            crate_name: Some("libzfs".into()),
+                        },
+                    ],
+                }),
+                warnings: vec![],
+                artifacts: HashMap::new(),
+            },
+        );
     }
 
     #[test]
@@ -1040,39 +4292,95 @@ To learn more, run the command again with --verbose.
 ";
 
         assert_done(
-            cargo_test_result_parser(output),
-            vec![
-                  Suite {
-                    name: "unknown",
-                    state: "fail",
-                    passed: 0,
-                    failed: 1,
-                    ignored: 0,
-                    measured: 0,
-                    total: 1,
-                    tests: vec![
-                        Test {
-                            name: "compile failed",
-                            status: "fail",
-                            error: Some("binary operation `==` cannot be applied to type `std::result::Result<nvpair::NvData, std::io::Error>`
-   --> libzfs/src/lib.rs:134:9
-    |
-134 |         assert_eq!(state, Ok(nvpair::NvData::Uint64(sys::pool_state::POOL_STATE_EXPORTED as u64)));
-    |         ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
-    |
-    = note: an implementation of `std::cmp::PartialEq` might be missing for `std::result::Result<nvpair::NvData, std::io::Error>`
-    = note: this error originates in a macro outside of the current crate
+            parse_run(output),
+            TestRun {
+                suites: vec![],
+                build_failure: Some(BuildFailure {
+                    crate_name: Some("libzfs".into()),
+                    diagnostics: vec![
+                        Diagnostic {
+                            code: Some("E0369".into()),
+                            message: "binary operation `==` cannot be applied to type \
+                                      `std::result::Result<nvpair::NvData, std::io::Error>`"
+                                .into(),
+                            location: Some(SourceLocation {
+                                file: "libzfs/src/lib.rs".into(),
+                                line: 134,
+                                column: Some(9),
+                            }),
+                            notes: vec![
+                                "note: an implementation of `std::cmp::PartialEq` might be missing for \
+                                 `std::result::Result<nvpair::NvData, std::io::Error>`"
+                                    .into(),
+                                "note: this error originates in a macro outside of the current crate"
+                                    .into(),
+                            ],
+                            crate_name: Some("libzfs".into()),
+                        },
+                    ],
+                }),
+                warnings: vec![],
+                artifacts: HashMap::new(),
+            },
+        );
+    }
 
-error: aborting due to previous error
+    #[test]
+    fn compile_fail_with_multiple_errors() {
+        let output = b"   Compiling foo v0.1.0 (/foo)
+error[E0425]: cannot find value `y` in this scope
+ --> src/lib.rs:3:13
+  |
+3 |     let x = y;
+  |             ^ not found in this scope
 
-error: Could not compile `libzfs`.
+error[E0308]: mismatched types
+ --> src/lib.rs:7:18
+  |
+7 |     let z: i32 = \"a\";
+  |            ---   ^^^ expected `i32`, found `&str`
+  |            |
+  |            expected due to this
 
-To learn more, run the command again with --verbose.
-")
+Some errors have detailed explanations: E0308, E0425.
+For more information about an error, try `rustc --explain E0308`.
+error: could not compile `foo` (lib test) due to 2 previous errors
+";
+
+        assert_done(
+            parse_run(output),
+            TestRun {
+                suites: vec![],
+                build_failure: Some(BuildFailure {
+                    crate_name: Some("foo".into()),
+                    diagnostics: vec![
+                        Diagnostic {
+                            code: Some("E0425".into()),
+                            message: "cannot find value `y` in this scope".into(),
+                            location: Some(SourceLocation {
+                                file: "src/lib.rs".into(),
+                                line: 3,
+                                column: Some(13),
+                            }),
+                            notes: vec![],
+                            crate_name: Some("foo".into()),
                         },
-                    ]
-                }
-                      ],
+                        Diagnostic {
+                            code: Some("E0308".into()),
+                            message: "mismatched types".into(),
+                            location: Some(SourceLocation {
+                                file: "src/lib.rs".into(),
+                                line: 7,
+                                column: Some(18),
+                            }),
+                            notes: vec![],
+                            crate_name: Some("foo".into()),
+                        },
+                    ],
+                }),
+                warnings: vec![],
+                artifacts: HashMap::new(),
+            },
         );
     }
 }
@@ -0,0 +1,467 @@
+extern crate nom;
+extern crate test_to_vec;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::{self, Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use nom::IResult;
+use test_to_vec::{cargo_test_result_parser, merge, nextest_result_parser, normalize, report, Event,
+                  Interleaver, StreamParser, SuiteBlock, TestRun};
+
+const USAGE: &str = "usage: test-to-vec [OPTIONS] [FILE|-]...
+       test-to-vec [OPTIONS] cargo [ARGS...]
+
+Reads `cargo test` output from FILE, or stdin when FILE is omitted or `-`,
+and prints the parsed suites. Given several files, as from a run split
+across machines, merges them into one report. Exits 1 when any suite or the
+build failed.
+
+With `cargo`, runs `cargo test ARGS...` instead, passing its output through
+unchanged, and writes the report to stderr once it exits. Exits with cargo's
+exit code.
+
+options:
+    -f, --format FORMAT    json, junit, tap or summary (default: json)
+    -o, --output FILE      write the report to FILE
+        --nextest          read `cargo nextest run` output instead; cannot be
+                           combined with `cargo`";
+
+#[derive(Clone, Copy)]
+enum Format {
+    Json,
+    Junit,
+    Tap,
+    Summary,
+}
+
+impl Format {
+    fn from_name(x: &str) -> Option<Format> {
+        match x {
+            "json" => Some(Format::Json),
+            "junit" => Some(Format::Junit),
+            "tap" => Some(Format::Tap),
+            "summary" => Some(Format::Summary),
+            _ => None,
+        }
+    }
+
+    fn render(self, run: &TestRun) -> String {
+        match self {
+            Format::Json => report::to_json(run) + "\n",
+            Format::Junit => report::to_junit(run),
+            Format::Tap => report::to_tap(run),
+            Format::Summary => report::to_summary(run),
+        }
+    }
+}
+
+struct Options {
+    format: Format,
+    output: Option<String>,
+    /// Files to read, where `-` is stdin.
+    inputs: Vec<String>,
+    nextest: bool,
+    /// Arguments for `cargo test`, when running it rather than reading a log.
+    cargo: Option<Vec<String>>,
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("test-to-vec: {}\n\n{}", message, USAGE);
+    process::exit(2);
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Options {
+    let mut options = Options {
+        format: Format::Json,
+        output: None,
+        inputs: vec![],
+        nextest: false,
+        cargo: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-f" | "--format" => {
+                let name = args.next()
+                    .unwrap_or_else(|| usage_error("--format needs a value"));
+                options.format = Format::from_name(&name)
+                    .unwrap_or_else(|| usage_error(&format!("unknown format `{}`", name)));
+            }
+            x if x.starts_with("--format=") => {
+                let name = &x["--format=".len()..];
+                options.format = Format::from_name(name)
+                    .unwrap_or_else(|| usage_error(&format!("unknown format `{}`", name)));
+            }
+            "-o" | "--output" => {
+                options.output = Some(args.next()
+                    .unwrap_or_else(|| usage_error("--output needs a value")));
+            }
+            x if x.starts_with("--output=") => {
+                options.output = Some(x["--output=".len()..].to_string());
+            }
+            "--nextest" => options.nextest = true,
+            "cargo" if options.inputs.is_empty() => {
+                options.cargo = Some(args.collect());
+                break;
+            }
+            x if x.starts_with('-') && x != "-" => usage_error(&format!("unknown option `{}`", x)),
+            _ => options.inputs.push(arg),
+        }
+    }
+
+    // The wrapper only knows how to run `cargo test`.
+    if options.nextest && options.cargo.is_some() {
+        usage_error("--nextest can't be used with `cargo`");
+    }
+
+    options
+}
+
+fn read_input(input: &str) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+
+    match input {
+        "-" => io::stdin().read_to_end(&mut buf)?,
+        path => File::open(path)?.read_to_end(&mut buf)?,
+    };
+
+    Ok(buf)
+}
+
+fn write_report(options: &Options, run: &TestRun, fallback: &mut dyn Write) {
+    let out = options.format.render(run);
+
+    let written = match options.output {
+        Some(ref path) => File::create(path).and_then(|mut x| x.write_all(out.as_bytes())),
+        None => fallback.write_all(out.as_bytes()),
+    };
+
+    if let Err(e) = written {
+        eprintln!("test-to-vec: could not write report: {}", e);
+        process::exit(2);
+    }
+}
+
+/// Copies lines from `from` to `to` as they arrive, and sends each one on to
+/// be parsed. After a write error the rest is still read and sent, so that
+/// cargo doesn't block on a full pipe, and the error is returned at the end.
+fn tee<R: Read, W: Write>(
+    from: R,
+    mut to: W,
+    is_stdout: bool,
+    lines: Sender<(bool, Vec<u8>)>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(from);
+    let mut written = Ok(());
+
+    loop {
+        let mut line = vec![];
+
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => return written,
+            Ok(_) => {
+                if written.is_ok() {
+                    written = to.write_all(&line).and_then(|_| to.flush());
+                }
+
+                // The receiver outlives both pipes.
+                let _ = lines.send((is_stdout, line));
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return written.and(Err(e)),
+        }
+    }
+}
+
+fn join_tee(name: &str, handle: thread::JoinHandle<io::Result<()>>) {
+    if let Ok(Err(e)) = handle.join() {
+        eprintln!("test-to-vec: could not pass cargo's {} through: {}", name, e);
+    }
+}
+
+/// Parses lines from cargo's stderr and stdout, each flagged with whether it
+/// came from stdout, as they arrive. Returns the build output and the suites
+/// once the lines run out.
+fn parse_lines<I: IntoIterator<Item = (bool, Vec<u8>)>>(lines: I) -> (Vec<u8>, Vec<SuiteBlock>) {
+    let mut order = Interleaver::new();
+    let mut parser = StreamParser::new();
+    let mut build_output = vec![];
+    let mut blocks = vec![];
+
+    let mut handle = |events: Vec<Event>| {
+        for event in events {
+            match event {
+                Event::BuildLine(x) => {
+                    build_output.extend_from_slice(x.as_bytes());
+                    build_output.push(b'\n');
+                }
+                Event::SuiteFinished(x) => blocks.push(x),
+                _ => {}
+            }
+        }
+    };
+
+    for (is_stdout, line) in lines {
+        let ready = if is_stdout { order.stdout(&line) } else { order.stderr(&line) };
+        handle(parser.feed(&ready));
+    }
+
+    handle(parser.feed(&order.finish()));
+    handle(parser.finish());
+
+    (build_output, blocks)
+}
+
+fn run_cargo(options: &Options, args: &[String]) -> ! {
+    let mut child = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+        .arg("test")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|e| {
+            eprintln!("test-to-vec: could not run cargo: {}", e);
+            process::exit(2);
+        });
+
+    // Cargo prints its own progress on stderr and the test binaries' output
+    // on stdout. Each pipe is read on a thread of its own, and the lines are
+    // parsed here as they come in.
+    let (sender, lines) = mpsc::channel();
+
+    let stdout = child.stdout.take().expect("cargo's stdout is piped");
+    let stdout_lines = sender.clone();
+    let out = thread::spawn(move || tee(stdout, io::stdout(), true, stdout_lines));
+
+    let stderr = child.stderr.take().expect("cargo's stderr is piped");
+    let err = thread::spawn(move || tee(stderr, io::stderr(), false, sender));
+
+    let (build_output, blocks) = parse_lines(lines);
+
+    join_tee("stdout", out);
+    join_tee("stderr", err);
+
+    let status = child.wait().unwrap_or_else(|e| {
+        eprintln!("test-to-vec: could not wait for cargo: {}", e);
+        process::exit(2);
+    });
+
+    let run = TestRun::from_stream(&build_output, &blocks);
+
+    write_report(options, &run, &mut io::stderr());
+
+    process::exit(status.code().unwrap_or(1));
+}
+
+fn has_failures(run: &TestRun) -> bool {
+    run.build_failure.is_some() || run.suites.iter().any(|x| x.state == "fail")
+}
+
+fn main() {
+    let options = parse_args(env::args().skip(1));
+
+    if let Some(ref args) = options.cargo {
+        run_cargo(&options, args);
+    }
+
+    let inputs = if options.inputs.is_empty() {
+        vec!["-".to_string()]
+    } else {
+        options.inputs.clone()
+    };
+
+    let bufs: Vec<Vec<u8>> = inputs
+        .iter()
+        .map(|x| {
+            let buf = read_input(x).unwrap_or_else(|e| {
+                eprintln!("test-to-vec: could not read {}: {}", x, e);
+                process::exit(2);
+            });
+
+            normalize(&buf).into_owned()
+        })
+        .collect();
+
+    let runs = bufs
+        .iter()
+        .zip(&inputs)
+        .map(|(buf, input)| {
+            let parsed = if options.nextest {
+                nextest_result_parser(buf)
+            } else {
+                cargo_test_result_parser(buf)
+            };
+
+            match parsed {
+                IResult::Done(_, x) => x,
+                _ => {
+                    eprintln!("test-to-vec: could not parse {} as cargo test output", input);
+                    process::exit(2);
+                }
+            }
+        })
+        .collect();
+
+    let run = merge(runs);
+
+    write_report(&options, &run, &mut io::stdout());
+
+    if has_failures(&run) {
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_to_vec::{cargo_test_result_parser, TestRun};
+
+    use std::sync::mpsc;
+    use std::thread;
+
+    use super::{has_failures, parse_args, parse_lines, Format, Options};
+
+    fn args(xs: &[&str]) -> Options {
+        parse_args(xs.iter().map(|x| x.to_string()))
+    }
+
+    #[test]
+    fn it_should_parse_options_and_inputs() {
+        let options = args(&["--format", "junit", "-o", "report.xml", "a.log", "-", "--nextest"]);
+
+        assert!(matches!(options.format, Format::Junit));
+        assert_eq!(options.output, Some("report.xml".to_string()));
+        assert_eq!(options.inputs, vec!["a.log", "-"]);
+        assert!(options.nextest);
+        assert_eq!(options.cargo, None);
+
+        let options = args(&[]);
+
+        assert!(matches!(options.format, Format::Json));
+        assert!(options.inputs.is_empty());
+    }
+
+    #[test]
+    fn it_should_pass_the_arguments_after_cargo_through() {
+        let options = args(&["--format=summary", "--output=report.txt", "cargo", "--lib", "--", "-f"]);
+
+        assert!(matches!(options.format, Format::Summary));
+        assert_eq!(options.output, Some("report.txt".to_string()));
+        assert_eq!(
+            options.cargo,
+            Some(vec!["--lib".to_string(), "--".to_string(), "-f".to_string()])
+        );
+
+        // A file can be called `cargo` too.
+        let options = args(&["a.log", "cargo"]);
+
+        assert_eq!(options.inputs, vec!["a.log", "cargo"]);
+        assert_eq!(options.cargo, None);
+    }
+
+    #[test]
+    fn it_should_fail_on_failed_suites_and_builds() {
+        let passed = cargo_test_result_parser(b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 1 test
+test tests::it_passes ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+").unwrap().1;
+
+        let failed = cargo_test_result_parser(b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 1 test
+test tests::it_fails ... FAILED
+
+failures:
+
+failures:
+    tests::it_fails
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+").unwrap().1;
+
+        let unbuilt = cargo_test_result_parser(b"   Compiling foo v0.1.0 (file:///foo)
+error[E0425]: cannot find value `x` in this scope
+ --> src/lib.rs:2:5
+  |
+2 |     x
+  |     ^ not found in this scope
+
+error: could not compile `foo`
+").unwrap().1;
+
+        assert!(!has_failures(&passed));
+        assert!(has_failures(&failed));
+        assert!(has_failures(&unbuilt));
+    }
+
+    #[test]
+    fn it_should_parse_cargo_output_in_pieces() {
+        let (sender, lines) = mpsc::channel();
+
+        let stderr = b"   Compiling foo v0.1.0 (file:///foo)
+     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+error: test failed, to rerun pass `--lib`
+";
+
+        let stdout = b"
+running 1 test
+test tests::it_fails ... FAILED
+
+failures:
+
+---- tests::it_fails stdout ----
+thread 'tests::it_fails' panicked at src/lib.rs:6:9:
+boom
+
+
+failures:
+    tests::it_fails
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+";
+
+        // The test binary's output starts before cargo's `Running` line
+        // arrives, the way two threads reading the pipes can deliver it.
+        let writer = thread::spawn(move || {
+            let mut stderr = stderr.split_inclusive(|&c| c == b'\n');
+
+            sender.send((false, stderr.next().unwrap().to_vec())).unwrap();
+
+            for line in stdout.split_inclusive(|&c| c == b'\n').take(3) {
+                sender.send((true, line.to_vec())).unwrap();
+            }
+
+            for line in stderr {
+                sender.send((false, line.to_vec())).unwrap();
+            }
+
+            for line in stdout.split_inclusive(|&c| c == b'\n').skip(3) {
+                sender.send((true, line.to_vec())).unwrap();
+            }
+        });
+
+        let (build_output, blocks) = parse_lines(lines);
+        writer.join().unwrap();
+
+        assert_eq!(blocks.len(), 1);
+
+        let run = TestRun::from_stream(&build_output, &blocks);
+        let suite = &run.suites[0];
+
+        assert_eq!(suite.name, "target/debug/deps/foo-5a7be5d1b9c8e0f6");
+        assert_eq!((suite.state, suite.failed), ("fail", 1));
+        assert_eq!(suite.tests[0].error, Some("thread 'tests::it_fails' panicked at src/lib.rs:6:9:\nboom"));
+        assert_eq!(suite.rerun_args, vec!["--lib"]);
+    }
+}
@@ -0,0 +1,293 @@
+//! Combines the runs of a test suite that was split across machines, with
+//! filters or `--partition`, into a single run.
+
+use std::collections::HashMap;
+
+use super::{count_status, Suite, Test, TestRun};
+
+// Which outcome to keep when shards disagree about a test: the one that
+// needs looking at.
+fn severity(status: &str) -> i64 {
+    match status {
+        "fail" => 3,
+        "incomplete" => 2,
+        "pass" | "measured" => 1,
+        _ => 0,
+    }
+}
+
+fn push_unique<T: PartialEq>(xs: &mut Vec<T>, x: T) {
+    if !xs.contains(&x) {
+        xs.push(x);
+    }
+}
+
+// Adds what `xs` doesn't have yet, keeping any repeats within `ys` itself.
+fn extend_unique<T: PartialEq>(xs: &mut Vec<T>, ys: Vec<T>) {
+    let seen = xs.len();
+
+    for y in ys {
+        if !xs[..seen].contains(&y) {
+            xs.push(y);
+        }
+    }
+}
+
+// Returns how many of `tests` the suite already had.
+fn merge_tests<'a>(suite: &mut Suite<'a, 'a, 'a, 'a, 'a>, tests: Vec<Test<'a, 'a, 'a>>) -> i64 {
+    let mut repeats = 0;
+
+    let mut index: HashMap<&str, usize> = suite
+        .tests
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.name, i))
+        .collect();
+
+    for t in tests {
+        let i = match index.get(t.name) {
+            Some(&i) => i,
+            None => {
+                index.insert(t.name, suite.tests.len());
+                suite.tests.push(t);
+                continue;
+            }
+        };
+
+        let existing = &mut suite.tests[i];
+        repeats += 1;
+
+        if existing.status == t.status {
+            suite.warnings.push(format!("`{}` ran in more than one shard", t.name));
+            continue;
+        }
+
+        suite.warnings.push(format!(
+            "`{}` has conflicting outcomes across shards: {} and {}",
+            t.name,
+            existing.status,
+            t.status
+        ));
+
+        if severity(t.status) > severity(existing.status) {
+            *existing = t;
+        }
+    }
+
+    repeats
+}
+
+fn merge_suite<'a>(suite: &mut Suite<'a, 'a, 'a, 'a, 'a>, other: Suite<'a, 'a, 'a, 'a, 'a>) {
+    if other.state != "pass" {
+        suite.state = other.state;
+    }
+
+    suite.planned += other.planned;
+
+    for x in other.slow {
+        push_unique(&mut suite.slow, x);
+    }
+
+    for x in other.failure_names {
+        push_unique(&mut suite.failure_names, x);
+    }
+
+    if suite.rerun_args.is_empty() {
+        suite.rerun_args = other.rerun_args;
+    }
+
+    suite.warnings.extend(other.warnings);
+
+    // A test that ran in more than one shard is only counted once, with the
+    // outcome that was kept.
+    suite.planned -= merge_tests(suite, other.tests);
+    suite.passed = count_status(&suite.tests, "pass");
+    suite.failed = count_status(&suite.tests, "fail");
+    suite.ignored = count_status(&suite.tests, "ignored");
+    suite.measured = count_status(&suite.tests, "measured");
+    suite.total = suite.tests.len() as i64;
+    suite.missing = (suite.planned - suite.total).max(0);
+}
+
+/// Combines several runs into one. Suites with the same name are merged,
+/// with their tests unioned and counted again. A test reported by more
+/// than one run is flagged in its suite's warnings, and when the outcomes
+/// conflict the failing one is kept.
+pub fn merge<'a>(runs: Vec<TestRun<'a>>) -> TestRun<'a> {
+    let mut merged = TestRun {
+        suites: vec![],
+        build_failure: None,
+        warnings: vec![],
+        artifacts: HashMap::new(),
+    };
+
+    let mut index: HashMap<&str, usize> = HashMap::new();
+
+    for run in runs {
+        for suite in run.suites {
+            match index.get(suite.name) {
+                Some(&i) => merge_suite(&mut merged.suites[i], suite),
+                None => {
+                    index.insert(suite.name, merged.suites.len());
+                    merged.suites.push(suite);
+                }
+            }
+        }
+
+        merged.build_failure = match (merged.build_failure.take(), run.build_failure) {
+            (Some(mut x), Some(y)) => {
+                extend_unique(&mut x.diagnostics, y.diagnostics);
+                Some(x)
+            }
+            (x, y) => x.or(y),
+        };
+
+        // Every shard builds the same code, and warns about the same things.
+        extend_unique(&mut merged.warnings, run.warnings);
+
+        merged.artifacts.extend(run.artifacts);
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{cargo_test_result_parser, parsed};
+    use super::merge;
+
+    #[test]
+    fn it_should_merge_shards() {
+        let first = parsed(cargo_test_result_parser(b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::one ... ok
+test tests::two ... ok
+
+test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 2 filtered out
+
+"));
+
+        let second = parsed(cargo_test_result_parser(b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::three ... FAILED
+test tests::four ... ignored
+
+failures:
+
+---- tests::three stdout ----
+thread 'tests::three' panicked at 'boom', src/lib.rs:30
+
+
+failures:
+    tests::three
+
+test result: FAILED. 0 passed; 1 failed; 1 ignored; 0 measured; 2 filtered out
+
+     Running target/debug/deps/bar-0c3c5a3b0c7e1a2b
+
+running 1 test
+test it_works ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+"));
+
+        let run = merge(vec![first, second]);
+
+        let names: Vec<&str> = run.suites.iter().map(|x| x.name).collect();
+        assert_eq!(
+            names,
+            vec!["target/debug/deps/foo-5a7be5d1b9c8e0f6", "target/debug/deps/bar-0c3c5a3b0c7e1a2b"]
+        );
+
+        let foo = &run.suites[0];
+        assert_eq!(foo.state, "fail");
+        assert_eq!(
+            (foo.passed, foo.failed, foo.ignored, foo.total, foo.planned, foo.missing),
+            (2, 1, 1, 4, 4, 0)
+        );
+        assert_eq!(foo.failure_names, vec!["tests::three"]);
+        assert!(foo.warnings.is_empty());
+
+        let tests: Vec<&str> = foo.tests.iter().map(|x| x.name).collect();
+        assert_eq!(tests, vec!["tests::one", "tests::two", "tests::three", "tests::four"]);
+    }
+
+    #[test]
+    fn it_should_count_a_repeated_test_once() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 1 test
+test tests::one ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+";
+
+        let run = merge(vec![
+            parsed(cargo_test_result_parser(output)),
+            parsed(cargo_test_result_parser(output)),
+        ]);
+        let foo = &run.suites[0];
+
+        assert_eq!(foo.tests.len(), 1);
+        assert_eq!(
+            (foo.passed, foo.failed, foo.ignored, foo.total, foo.planned, foo.missing),
+            (1, 0, 0, 1, 1, 0)
+        );
+        assert_eq!(foo.warnings, vec!["`tests::one` ran in more than one shard"]);
+    }
+
+    #[test]
+    fn it_should_flag_conflicting_outcomes() {
+        let first = parsed(cargo_test_result_parser(b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::flaky ... FAILED
+test tests::steady ... ok
+
+failures:
+
+---- tests::flaky stdout ----
+thread 'tests::flaky' panicked at 'boom', src/lib.rs:30
+
+
+failures:
+    tests::flaky
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+
+"));
+
+        let second = parsed(cargo_test_result_parser(b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::flaky ... ok
+test tests::steady ... ok
+
+test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+"));
+
+        let run = merge(vec![second, first]);
+        let foo = &run.suites[0];
+
+        assert_eq!(foo.state, "fail");
+        assert_eq!(
+            foo.warnings,
+            vec![
+                "`tests::flaky` has conflicting outcomes across shards: pass and fail",
+                "`tests::steady` ran in more than one shard",
+            ]
+        );
+        assert_eq!(foo.tests.len(), 2);
+        assert_eq!(
+            (foo.passed, foo.failed, foo.ignored, foo.total, foo.planned, foo.missing),
+            (1, 1, 0, 2, 2, 0)
+        );
+        assert_eq!(foo.tests[0].status, "fail");
+        assert_eq!(foo.tests[0].error, Some("thread 'tests::flaky' panicked at 'boom', src/lib.rs:30"));
+    }
+}
@@ -0,0 +1,590 @@
+//! Parses the human readable output of `cargo nextest run`, which runs each
+//! test in a process of its own and reports it on a single line.
+
+use std::collections::HashMap;
+use std::str;
+
+use nom::IResult;
+
+use super::assertion::parse_assertion;
+use super::backtrace::split_backtrace;
+use super::failure_kind::{failure_kind, FailureKind};
+use super::{build_item, count_status, failure, handle_test_run, panic_location, split_panic_message,
+            BuildItem, Suite, Test, TestRun};
+
+named!(
+    build_items<Vec<BuildItem<'a>> >,
+    many0!(build_item)
+);
+
+struct StatusLine<'a> {
+    attempt: i64,
+    status: &'a str,
+    duration: Option<f64>,
+    binary: &'a str,
+    name: &'a str,
+}
+
+fn is_status(x: &str) -> bool {
+    match x {
+        "PASS" | "FAIL" | "LEAK" | "LEAK-FAIL" | "SLOW" | "TIMEOUT" | "ABORT" | "SKIP" => true,
+        _ => x.len() > 3 && x.starts_with("SIG") && x[3..].chars().all(|c| c.is_ascii_uppercase()),
+    }
+}
+
+// `PASS [   0.004s] demo tests::it_works`, prefixed with `TRY 2` when the
+// test was retried. Newer versions put a `(1/5)` counter before the binary.
+fn status_line(line: &str) -> Option<StatusLine<'_>> {
+    let mut rest = line.trim();
+    let mut attempt = 1;
+
+    if let Some(x) = rest.strip_prefix("TRY ") {
+        let (n, x) = x.split_once(' ')?;
+        attempt = n.parse().ok()?;
+        rest = x.trim_start();
+    }
+
+    let (status, rest) = rest.split_once(' ')?;
+
+    if !is_status(status) {
+        return None;
+    }
+
+    let rest = rest.trim_start().strip_prefix('[')?;
+    let end = rest.find(']')?;
+
+    // Slow tests show `[> 60.000s]`, how long they have been running for.
+    let duration = rest[..end]
+        .trim()
+        .trim_start_matches('>')
+        .trim()
+        .trim_end_matches('s')
+        .parse()
+        .ok();
+
+    let mut rest = rest[end + 1..].trim_start();
+
+    if rest.starts_with('(') {
+        rest = rest.split_once(") ")?.1.trim_start();
+    }
+
+    let (binary, name) = rest.split_once(' ')?;
+
+    Some(StatusLine {
+        attempt,
+        status,
+        duration,
+        binary,
+        name: name.trim(),
+    })
+}
+
+// `--- STDOUT:              demo tests::it_fails ---`, or `--- TRY 1 STDERR:`
+// for an attempt that was retried.
+fn output_header(line: &str) -> Option<(bool, &str, &str)> {
+    let inner = line.trim().strip_prefix("--- ")?.strip_suffix(" ---")?;
+
+    let inner = match inner.strip_prefix("TRY ") {
+        Some(x) => x.split_once(' ')?.1,
+        None => inner,
+    };
+
+    let (stream, rest) = inner.split_once(':')?;
+
+    let is_stdout = match stream {
+        "STDOUT" => true,
+        "STDERR" => false,
+        _ => return None,
+    };
+
+    let (binary, name) = rest.trim().split_once(' ')?;
+
+    Some((is_stdout, binary, name.trim()))
+}
+
+fn is_summary(line: &str) -> bool {
+    line.trim_start().starts_with("Summary [")
+}
+
+// Lines after which a test's captured output is over.
+fn ends_output(line: &str) -> bool {
+    let line = line.trim();
+
+    line == "------------" || line.starts_with("RETRY ") || line.starts_with("Cancelling ") ||
+        is_summary(line) || status_line(line).is_some() || output_header(line).is_some()
+}
+
+fn nextest_status(x: &str) -> &'static str {
+    match x {
+        "PASS" | "LEAK" => "pass",
+        "SKIP" => "ignored",
+        _ => "fail",
+    }
+}
+
+struct Entry<'a> {
+    binary: &'a str,
+    test: Test<'a, 'a, 'a>,
+    stdout: Option<&'a str>,
+    stderr: Option<&'a str>,
+    leaked: bool,
+}
+
+impl<'a> Entry<'a> {
+    fn new(binary: &'a str, name: &'a str) -> Entry<'a> {
+        Entry {
+            binary,
+            test: Test {
+                name,
+                status: "incomplete",
+                error: None,
+                slow_warning: false,
+                stdout: None,
+                location: None,
+                assertion: None,
+                backtrace: vec![],
+                failure_kind: None,
+                duration: None,
+                retries: 0,
+            },
+            stdout: None,
+            stderr: None,
+            leaked: false,
+        }
+    }
+
+    fn record(&mut self, line: StatusLine<'a>) {
+        if line.status == "SLOW" {
+            self.test.slow_warning = true;
+            return;
+        }
+
+        self.test.status = nextest_status(line.status);
+        self.test.duration = line.duration;
+        self.test.retries = self.test.retries.max(line.attempt - 1);
+        self.leaked = line.status.starts_with("LEAK");
+
+        self.test.failure_kind = match line.status {
+            "TIMEOUT" => Some(FailureKind::Timeout),
+            x if x.starts_with("SIG") || x == "ABORT" => Some(FailureKind::Signal(x)),
+            _ => None,
+        };
+
+        // Output printed for an earlier attempt doesn't belong to this one.
+        self.stdout = None;
+        self.stderr = None;
+    }
+
+    fn set_output(&mut self, is_stdout: bool, text: &'a str) {
+        if is_stdout {
+            self.stdout = Some(text.trim());
+        } else {
+            self.stderr = Some(text.trim());
+        }
+    }
+
+    fn finish(self) -> Test<'a, 'a, 'a> {
+        let mut test = self.test;
+
+        if test.status != "fail" {
+            return test;
+        }
+
+        let stdout = self.stdout.unwrap_or("");
+        let stderr = self.stderr.unwrap_or("");
+
+        test.stdout = captured_stdout(test.name, stdout);
+
+        // libtest itself reports `#[should_panic]` mismatches on stdout.
+        let kind = match failure_kind(stdout) {
+            x @ FailureKind::DidNotPanic | x @ FailureKind::WrongPanic { .. } => x,
+            _ => failure_kind(stderr),
+        };
+
+        test.failure_kind = test.failure_kind.or(Some(kind));
+
+        if !stderr.is_empty() {
+            let (output, backtrace) = split_backtrace(stderr);
+            let error = split_panic_message(test.name, output).1;
+
+            test.error = Some(error);
+            test.backtrace = backtrace;
+        } else if let Some(i) = stdout.find("---- ") {
+            if let IResult::Done(_, x) = failure(&stdout.as_bytes()[i..]) {
+                test.error = Some(x.error);
+                test.backtrace = x.backtrace;
+            }
+        }
+
+        test.location = test.error.and_then(panic_location);
+        test.assertion = test.error.and_then(parse_assertion);
+        test
+    }
+}
+
+// What the test printed, without libtest's `running 1 test` and the report
+// around it.
+fn captured_stdout<'a>(name: &str, block: &'a str) -> Option<&'a str> {
+    let mut text = match block.find("running 1 test\n") {
+        Some(i) => &block[i + "running 1 test\n".len()..],
+        None => block,
+    };
+
+    for end in &["\nfailures:", "\nsuccesses:", "\ntest result: "] {
+        if let Some(i) = text.find(end) {
+            text = &text[..i];
+        }
+    }
+
+    let verdict = format!("test {} ...", name);
+    let mut text = text.trim();
+
+    if text.starts_with(&verdict) {
+        text = text.split_once('\n').map_or("", |x| x.1);
+    }
+
+    if let Some(i) = text.rfind('\n') {
+        if text[i + 1..].starts_with(&verdict) {
+            text = &text[..i];
+        }
+    }
+
+    let text = text.trim();
+
+    if text.is_empty() || text.starts_with(&verdict) {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+fn entry_index<'a>(
+    entries: &mut Vec<Entry<'a>>,
+    index: &mut HashMap<(&'a str, &'a str), usize>,
+    binary: &'a str,
+    name: &'a str,
+) -> usize {
+    *index.entry((binary, name)).or_insert_with(|| {
+        entries.push(Entry::new(binary, name));
+        entries.len() - 1
+    })
+}
+
+// `Starting N tests across M binaries`, as `(N, M)`.
+fn starting_counts(line: &str) -> Option<(i64, i64)> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["Starting", tests, _, "across", binaries, ..] => Some((tests.parse().ok()?, binaries.parse().ok()?)),
+        _ => None,
+    }
+}
+
+// `planned` is only known when the run had a single binary; nextest doesn't
+// say how its tests were split otherwise.
+fn build_suite<'a>(
+    binary: &'a str,
+    entries: Vec<Entry<'a>>,
+    planned: Option<i64>,
+) -> Suite<'a, 'a, 'a, 'a, 'a> {
+    let warnings = entries
+        .iter()
+        .filter(|x| x.leaked)
+        .map(|x| format!("`{}` leaked handles", x.test.name))
+        .collect();
+
+    let tests: Vec<Test> = entries.into_iter().map(Entry::finish).collect();
+
+    let passed = count_status(&tests, "pass");
+    let failed = count_status(&tests, "fail");
+    let ignored = count_status(&tests, "ignored");
+    let incomplete = count_status(&tests, "incomplete");
+
+    let total = tests.len() as i64;
+    let planned = planned.unwrap_or(total);
+    let missing = (planned - total).max(0);
+
+    Suite {
+        name: binary,
+        state: if failed + incomplete + missing > 0 { "fail" } else { "pass" },
+        passed,
+        failed,
+        ignored,
+        measured: 0,
+        total,
+        planned,
+        missing,
+        slow: tests.iter().filter(|x| x.slow_warning).map(|x| x.name).collect(),
+        failure_names: vec![],
+        rerun_args: vec![],
+        warnings,
+        tests,
+    }
+}
+
+/// Parses the output of `cargo nextest run` into the same model as
+/// `cargo test`, with a suite for each test binary.
+/// Like [`cargo_test_result_parser`](super::cargo_test_result_parser), it
+/// expects input that went through [`normalize`](super::normalize).
+pub fn nextest_result_parser(input: &[u8]) -> IResult<&[u8], TestRun<'_>> {
+    let text = match str::from_utf8(input) {
+        Ok(x) => x,
+        Err(_) => return IResult::Error(error_position!(nom::ErrorKind::MapRes, input)),
+    };
+
+    // Everything before `Starting N tests across M binaries` is cargo building
+    // them. Without it, the build failed.
+    let mut start = text.len();
+    let mut offset = 0;
+    let mut counts = None;
+
+    for line in text.split_inclusive('\n') {
+        let x = line.trim();
+
+        if x.starts_with("Starting ") && x.contains(" across ") {
+            start = offset;
+            counts = starting_counts(x);
+            break;
+        }
+
+        offset += line.len();
+    }
+
+    let items = match build_items(&text.as_bytes()[..start]) {
+        IResult::Done(_, x) => x,
+        _ => vec![],
+    };
+
+    let mut entries: Vec<Entry> = vec![];
+    let mut index: HashMap<(&str, &str), usize> = HashMap::new();
+    // The entry whose output is being read, whether it's stdout, and where
+    // the output started.
+    let mut output: Option<(usize, bool, usize)> = None;
+    let mut offset = start;
+
+    for line in text[start..].split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        if ends_output(line) {
+            if let Some((i, is_stdout, from)) = output.take() {
+                entries[i].set_output(is_stdout, &text[from..line_start]);
+            }
+        }
+
+        // The summary lists the failures again.
+        if is_summary(line) {
+            break;
+        }
+
+        if let Some(x) = status_line(line) {
+            let i = entry_index(&mut entries, &mut index, x.binary, x.name);
+            entries[i].record(x);
+        } else if let Some((is_stdout, binary, name)) = output_header(line) {
+            let i = entry_index(&mut entries, &mut index, binary, name);
+            output = Some((i, is_stdout, offset));
+        }
+    }
+
+    if let Some((i, is_stdout, from)) = output {
+        entries[i].set_output(is_stdout, &text[from..offset]);
+    }
+
+    let mut binaries: Vec<&str> = vec![];
+    let mut grouped: HashMap<&str, Vec<Entry>> = HashMap::new();
+
+    for x in entries {
+        if !grouped.contains_key(x.binary) {
+            binaries.push(x.binary);
+        }
+        grouped.entry(x.binary).or_default().push(x);
+    }
+
+    let planned = match counts {
+        Some((tests, 1)) if binaries.len() == 1 => Some(tests),
+        _ => None,
+    };
+
+    let suites = binaries
+        .into_iter()
+        .map(|x| build_suite(x, grouped.remove(x).unwrap_or_default(), planned))
+        .collect();
+
+    IResult::Done(&input[input.len()..], handle_test_run(items, suites))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parsed, FailureKind, SourceLocation};
+    use super::nextest_result_parser;
+
+    #[test]
+    fn it_should_parse_a_nextest_run() {
+        let output = b"   Compiling demo v0.1.0 (/tmp/demo)
+    Finished test [unoptimized + debuginfo] target(s) in 0.52s
+    Starting 7 tests across 2 binaries (1 skipped)
+        PASS [   0.004s] demo tests::passes
+        FAIL [   0.005s] demo tests::fails
+
+--- STDOUT:              demo tests::fails ---
+
+running 1 test
+hello from fails
+test tests::fails ... FAILED
+
+failures:
+
+failures:
+    tests::fails
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 2 filtered out; finished in 0.00s
+
+
+--- STDERR:              demo tests::fails ---
+thread 'tests::fails' panicked at src/lib.rs:6:48:
+assertion `left == right` failed
+  left: 1
+ right: 2
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+   TRY 1 FAIL [   0.003s] demo tests::flaky
+
+--- TRY 1 STDERR:        demo tests::flaky ---
+thread 'tests::flaky' panicked at src/lib.rs:12:9:
+not yet
+
+  RETRY 2/2 [         ] demo tests::flaky
+   TRY 2 PASS [   0.002s] demo tests::flaky
+        SLOW [> 60.000s] demo::integration it_is_slow
+        PASS [  61.216s] demo::integration it_is_slow
+     SIGSEGV [   0.010s] demo::integration it_segfaults
+        LEAK [   0.005s] demo::integration it_leaks
+     TIMEOUT [ 120.002s] demo::integration it_hangs
+------------
+     Summary [ 181.240s] 7 tests run: 4 passed, 3 failed, 1 skipped
+        FAIL [   0.005s] demo tests::fails
+     SIGSEGV [   0.010s] demo::integration it_segfaults
+     TIMEOUT [ 120.002s] demo::integration it_hangs
+error: test run failed
+";
+
+        let run = parsed(nextest_result_parser(output));
+
+        assert_eq!(run.build_failure, None);
+
+        let names: Vec<&str> = run.suites.iter().map(|x| x.name).collect();
+        assert_eq!(names, vec!["demo", "demo::integration"]);
+
+        let demo = &run.suites[0];
+        assert_eq!((demo.state, demo.passed, demo.failed, demo.total), ("fail", 2, 1, 3));
+        // Two binaries share the 7 tests, in a split nextest doesn't print.
+        assert_eq!((demo.planned, demo.missing), (3, 0));
+
+        let fails = &demo.tests[1];
+        assert_eq!(fails.name, "tests::fails");
+        assert_eq!(fails.status, "fail");
+        assert_eq!(fails.duration, Some(0.005));
+        assert_eq!(fails.stdout, Some("hello from fails"));
+        assert_eq!(
+            fails.error,
+            Some("thread 'tests::fails' panicked at src/lib.rs:6:48:\nassertion `left == right` failed\n  left: 1\n right: 2")
+        );
+        assert_eq!(fails.failure_kind, Some(FailureKind::Panic));
+        assert_eq!(
+            fails.location,
+            Some(SourceLocation {
+                file: "src/lib.rs".into(),
+                line: 6,
+                column: Some(48),
+            })
+        );
+
+        let flaky = &demo.tests[2];
+        assert_eq!((flaky.status, flaky.retries, flaky.duration), ("pass", 1, Some(0.002)));
+        assert_eq!(flaky.error, None);
+
+        let integration = &run.suites[1];
+        assert_eq!(
+            (integration.passed, integration.failed, integration.missing),
+            (2, 2, 0)
+        );
+        assert_eq!(integration.slow, vec!["it_is_slow"]);
+        assert_eq!(integration.warnings, vec!["`it_leaks` leaked handles"]);
+        assert_eq!(integration.tests[0].duration, Some(61.216));
+        assert_eq!(integration.tests[1].failure_kind, Some(FailureKind::Signal("SIGSEGV")));
+        assert_eq!(integration.tests[3].failure_kind, Some(FailureKind::Timeout));
+    }
+
+    #[test]
+    fn it_should_count_tests_that_never_ran() {
+        let output = b"    Starting 3 tests across 1 binary
+        PASS [   0.004s] demo tests::passes
+     SIGKILL [   0.010s] demo tests::is_killed
+";
+
+        let run = parsed(nextest_result_parser(output));
+
+        let demo = &run.suites[0];
+        assert_eq!(demo.state, "fail");
+        assert_eq!((demo.total, demo.planned, demo.missing), (2, 3, 1));
+    }
+
+    #[test]
+    fn it_should_find_should_panic_tests_that_printed_an_error() {
+        let output = b"    Starting 1 test across 1 binary
+        FAIL [   0.004s] demo tests::retries
+
+--- STDOUT:              demo tests::retries ---
+
+running 1 test
+Error: retrying
+test tests::retries - should panic ... FAILED
+
+failures:
+
+---- tests::retries stdout ----
+note: test did not panic as expected at src/lib.rs:15:8
+
+failures:
+    tests::retries
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 2 filtered out; finished in 0.00s
+
+------------
+     Summary [   0.004s] 1 test run: 0 passed, 1 failed, 0 skipped
+        FAIL [   0.004s] demo tests::retries
+error: test run failed
+";
+
+        let run = parsed(nextest_result_parser(output));
+
+        let retries = &run.suites[0].tests[0];
+        assert_eq!(retries.failure_kind, Some(FailureKind::DidNotPanic));
+        assert_eq!(
+            retries.error,
+            Some("note: test did not panic as expected at src/lib.rs:15:8")
+        );
+    }
+
+    #[test]
+    fn it_should_parse_a_nextest_build_failure() {
+        let output = b"   Compiling demo v0.1.0 (/tmp/demo)
+error[E0425]: cannot find value `x` in this scope
+ --> src/lib.rs:2:5
+  |
+2 |     x
+  |     ^ not found in this scope
+
+error: could not compile `demo` due to previous error
+error: command `cargo test --no-run --message-format json-render-diagnostics` exited with code 101
+";
+
+        let run = parsed(nextest_result_parser(output));
+
+        assert!(run.suites.is_empty());
+        assert_eq!(
+            run.build_failure.map(|x| x.crate_name),
+            Some(Some("demo".into()))
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use std::borrow::Cow;
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+/// Removes ANSI escape sequences, like the colors from `--color=always`, so
+/// the output can be handed to the parsers. Input without any escapes is
+/// returned as is.
+pub fn strip_ansi(input: &[u8]) -> Cow<'_, [u8]> {
+    if !input.contains(&ESC) {
+        return Cow::Borrowed(input);
+    }
+
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+
+    while i < input.len() {
+        if input[i] != ESC {
+            out.push(input[i]);
+            i += 1;
+            continue;
+        }
+
+        i += 1;
+
+        match input.get(i) {
+            // CSI: parameters and intermediates, then one final byte.
+            Some(&b'[') => {
+                i += 1;
+                while i < input.len() && (0x20..0x40).contains(&input[i]) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            // OSC, used for hyperlinks: runs until BEL or ESC \.
+            Some(&b']') => {
+                i += 1;
+                while i < input.len() {
+                    if input[i] == BEL {
+                        i += 1;
+                        break;
+                    }
+                    if input[i] == ESC && input.get(i + 1) == Some(&b'\\') {
+                        i += 2;
+                        break;
+                    }
+                    i += 1;
+                }
+            }
+            Some(_) => i += 1,
+            None => {}
+        }
+    }
+
+    Cow::Owned(out)
+}
+
+/// Turns `\r\n` line endings into `\n`, for logs that passed through Windows.
+/// A lone `\r` is left alone. Input without any is returned as is.
+pub fn strip_carriage_returns(input: &[u8]) -> Cow<'_, [u8]> {
+    if !input.windows(2).any(|x| x == b"\r\n") {
+        return Cow::Borrowed(input);
+    }
+
+    let mut out = Vec::with_capacity(input.len());
+
+    for (i, &c) in input.iter().enumerate() {
+        if c != b'\r' || input.get(i + 1) != Some(&b'\n') {
+            out.push(c);
+        }
+    }
+
+    Cow::Owned(out)
+}
+
+/// Strips ANSI escape codes and `\r\n` line endings. The parsers expect
+/// plain output with `\n` line endings, so run logs of unknown origin through
+/// this first.
+pub fn normalize(input: &[u8]) -> Cow<'_, [u8]> {
+    match strip_ansi(input) {
+        Cow::Borrowed(x) => strip_carriage_returns(x),
+        Cow::Owned(x) => match strip_carriage_returns(&x) {
+            Cow::Borrowed(_) => Cow::Owned(x),
+            Cow::Owned(y) => Cow::Owned(y),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use nom::IResult;
+
+    use super::{normalize, strip_ansi, strip_carriage_returns};
+    use super::super::cargo_test_result_parser;
+
+    #[test]
+    fn it_should_borrow_plain_output() {
+        let output = b"test tests::it_passes ... ok\n";
+
+        assert_eq!(strip_ansi(output), Cow::Borrowed(&output[..]));
+    }
+
+    #[test]
+    fn it_should_strip_colors_and_hyperlinks() {
+        let output = b"\x1b[1m\x1b[32m   Compiling\x1b[0m foo v0.1.0 \
+\x1b]8;;file:///foo\x1b\\(file:///foo)\x1b]8;;\x1b\\\x1b[K\n";
+
+        assert_eq!(
+            &strip_ansi(output)[..],
+            &b"   Compiling foo v0.1.0 (file:///foo)\n"[..]
+        );
+    }
+
+    #[test]
+    fn it_should_parse_colored_output_like_plain_output() {
+        let plain = b"   Compiling foo v0.1.0 (file:///foo)
+    Finished debug [unoptimized + debuginfo] target(s) in 12.11 secs
+     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 3 tests
+test tests::it_passes ... ok
+test tests::it_fails ... FAILED
+test tests::it_is_ignored ... ignored
+
+failures:
+
+---- tests::it_fails stdout ----
+thread 'tests::it_fails' panicked at 'boom', src/lib.rs:10
+
+
+failures:
+    tests::it_fails
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+
+        let colored = b"\x1b[1m\x1b[32m   Compiling\x1b[0m foo v0.1.0 (file:///foo)
+\x1b[1m\x1b[32m    Finished\x1b[0m debug [unoptimized + debuginfo] target(s) in 12.11 secs
+\x1b[1m\x1b[32m     Running\x1b[0m target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 3 tests
+test tests::it_passes ... \x1b[32mok\x1b[0m
+test tests::it_fails ... \x1b[31mFAILED\x1b[0m
+test tests::it_is_ignored ... \x1b[33mignored\x1b[0m
+
+failures:
+
+---- tests::it_fails stdout ----
+thread 'tests::it_fails' panicked at 'boom', src/lib.rs:10
+
+
+failures:
+    tests::it_fails
+
+test result: \x1b[31mFAILED\x1b[0m. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+
+        let stripped = strip_ansi(colored);
+
+        match (cargo_test_result_parser(plain), cargo_test_result_parser(&stripped)) {
+            (IResult::Done(_, x), IResult::Done(_, y)) => assert_eq!(x, y),
+            x => panic!("expected both to parse, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn it_should_strip_carriage_returns_before_newlines_only() {
+        assert_eq!(
+            &strip_carriage_returns(b"running 1 test\r\nprogress\r50%\n\r\n")[..],
+            &b"running 1 test\nprogress\r50%\n\n"[..]
+        );
+    }
+
+    #[test]
+    fn it_should_normalize_colored_crlf_output() {
+        assert_eq!(
+            &normalize(b"test tests::it_passes ... \x1b[32mok\x1b[0m\r\n")[..],
+            &b"test tests::it_passes ... ok\n"[..]
+        );
+    }
+}
@@ -0,0 +1,599 @@
+//! Renders a parsed `TestRun` in formats other tools understand.
+
+use std::fmt::Write;
+
+use serde_json::{self, Value};
+
+use super::{count_status, Diagnostic, DurationChange, FailureKind, Frame, RunDiff, SourceLocation,
+            Suite, Test, TestId, TestRun};
+
+fn location_json(x: &SourceLocation) -> Value {
+    json!({
+        "file": x.file,
+        "line": x.line,
+        "column": x.column,
+    })
+}
+
+fn frame_json(x: &Frame) -> Value {
+    json!({
+        "index": x.index,
+        "symbol": x.symbol,
+        "file": x.file,
+        "line": x.line,
+        "internal": x.is_internal(),
+    })
+}
+
+fn failure_kind_json(x: FailureKind) -> Value {
+    match x {
+        FailureKind::Panic => json!({ "kind": "panic" }),
+        FailureKind::DidNotPanic => json!({ "kind": "did_not_panic" }),
+        FailureKind::WrongPanic { expected, actual } => json!({
+            "kind": "wrong_panic",
+            "expected": expected,
+            "actual": actual,
+        }),
+        FailureKind::ReturnedError(error) => json!({
+            "kind": "returned_error",
+            "error": error,
+        }),
+        FailureKind::Signal(signal) => json!({
+            "kind": "signal",
+            "signal": signal,
+        }),
+        FailureKind::Timeout => json!({ "kind": "timeout" }),
+    }
+}
+
+fn diagnostic_json(x: &Diagnostic) -> Value {
+    json!({
+        "code": x.code,
+        "message": x.message,
+        "location": x.location.as_ref().map(location_json),
+        "notes": x.notes,
+        "crate_name": x.crate_name,
+    })
+}
+
+fn test_json(x: &Test) -> Value {
+    json!({
+        "name": x.name,
+        "status": x.status,
+        "error": x.error,
+        "slow_warning": x.slow_warning,
+        "stdout": x.stdout,
+        "location": x.location.as_ref().map(location_json),
+        "assertion": x.assertion.as_ref().map(|a| json!({
+            "kind": a.kind,
+            "left": a.left,
+            "right": a.right,
+            "message": a.message,
+        })),
+        "backtrace": x.backtrace.iter().map(frame_json).collect::<Vec<_>>(),
+        "failure_kind": x.failure_kind.map(failure_kind_json),
+        "duration": x.duration,
+        "retries": x.retries,
+    })
+}
+
+fn suite_json(x: &Suite) -> Value {
+    json!({
+        "name": x.name,
+        "state": x.state,
+        "passed": x.passed,
+        "failed": x.failed,
+        "ignored": x.ignored,
+        "measured": x.measured,
+        "total": x.total,
+        "planned": x.planned,
+        "missing": x.missing,
+        "slow": x.slow,
+        "failure_names": x.failure_names,
+        "rerun_args": x.rerun_args,
+        "rerun_command": x.rerun_command(),
+        "warnings": x.warnings,
+        "tests": x.tests.iter().map(test_json).collect::<Vec<_>>(),
+    })
+}
+
+pub fn to_json(run: &TestRun) -> String {
+    let value = json!({
+        "suites": run.suites.iter().map(suite_json).collect::<Vec<_>>(),
+        "build_failure": run.build_failure.as_ref().map(|x| json!({
+            "crate_name": x.crate_name,
+            "diagnostics": x.diagnostics.iter().map(diagnostic_json).collect::<Vec<_>>(),
+        })),
+        "warnings": run.warnings.iter().map(diagnostic_json).collect::<Vec<_>>(),
+        "artifacts": run.artifacts,
+    });
+
+    serde_json::to_string_pretty(&value).expect("a json value always serializes")
+}
+
+fn xml_escape(x: &str) -> String {
+    let mut out = String::with_capacity(x.len());
+
+    for c in x.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // XML 1.0 can't represent other control characters at all.
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+// The `tests` attribute counts the tests a crashed suite never got to as
+// well, so they can be read back.
+fn declared_tests(suite: &Suite) -> i64 {
+    suite.planned.max(suite.tests.len() as i64)
+}
+
+pub fn to_junit(run: &TestRun) -> String {
+    let mut out = String::new();
+
+    let tests: i64 = run.suites.iter().map(declared_tests).sum();
+    let failures: i64 = run.suites.iter().map(|x| count_status(&x.tests, "fail")).sum();
+    let errors: i64 = run.suites.iter().map(|x| count_status(&x.tests, "incomplete")).sum();
+
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<testsuites tests=\"{}\" failures=\"{}\" errors=\"{}\">",
+        tests,
+        failures,
+        errors
+    );
+
+    if let Some(ref x) = run.build_failure {
+        let name = x.crate_name.as_ref().map_or("build", |x| x.as_ref());
+        let message = x.diagnostics
+            .iter()
+            .map(|d| d.message.as_ref())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let _ = writeln!(
+            out,
+            "  <testsuite name=\"{}\" tests=\"1\" failures=\"0\" errors=\"1\" skipped=\"0\">",
+            xml_escape(name)
+        );
+        let _ = writeln!(out, "    <testcase name=\"build\" classname=\"{}\">", xml_escape(name));
+        let _ = writeln!(
+            out,
+            "      <error message=\"build failed\">{}</error>",
+            xml_escape(&message)
+        );
+        out.push_str("    </testcase>\n");
+        out.push_str("  </testsuite>\n");
+    }
+
+    for suite in &run.suites {
+        let _ = writeln!(
+            out,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\">",
+            xml_escape(suite.name),
+            declared_tests(suite),
+            count_status(&suite.tests, "fail"),
+            count_status(&suite.tests, "incomplete"),
+            count_status(&suite.tests, "ignored")
+        );
+
+        for test in &suite.tests {
+            let mut open = format!(
+                "    <testcase name=\"{}\" classname=\"{}\"",
+                xml_escape(test.name),
+                xml_escape(suite.name)
+            );
+
+            if let Some(ref x) = test.location {
+                let _ = write!(open, " file=\"{}\" line=\"{}\"", xml_escape(&x.file), x.line);
+            }
+
+            if let Some(x) = test.duration {
+                let _ = write!(open, " time=\"{:.3}\"", x);
+            }
+
+            let system_out = test.stdout.map(|x| {
+                format!("      <system-out>{}</system-out>\n", xml_escape(x))
+            });
+
+            if (test.status == "pass" || test.status == "measured") && system_out.is_none() {
+                let _ = writeln!(out, "{}/>", open);
+                continue;
+            }
+
+            let _ = writeln!(out, "{}>", open);
+
+            match test.status {
+                "fail" => {
+                    let error = test.error.unwrap_or("");
+                    let _ = writeln!(
+                        out,
+                        "      <failure message=\"{}\">{}</failure>",
+                        xml_escape(error.lines().next().unwrap_or("")),
+                        xml_escape(error)
+                    );
+                }
+                "ignored" => out.push_str("      <skipped/>\n"),
+                "incomplete" => out.push_str("      <error message=\"test did not finish\"/>\n"),
+                _ => {}
+            }
+
+            out.push_str(&system_out.unwrap_or_default());
+            out.push_str("    </testcase>\n");
+        }
+
+        out.push_str("  </testsuite>\n");
+    }
+
+    out.push_str("</testsuites>\n");
+
+    out
+}
+
+pub fn to_tap(run: &TestRun) -> String {
+    let mut out = String::new();
+
+    let tests: usize = run.suites.iter().map(|x| x.tests.len()).sum();
+    let build = if run.build_failure.is_some() { 1 } else { 0 };
+
+    out.push_str("TAP version 13\n");
+    let _ = writeln!(out, "1..{}", tests + build);
+
+    if let Some(ref x) = run.build_failure {
+        out.push_str("not ok 1 - build\n");
+        out.push_str("  ---\n");
+        for d in &x.diagnostics {
+            let _ = writeln!(out, "  message: {}", serde_json::to_string(&d.message).unwrap());
+        }
+        out.push_str("  ...\n");
+    }
+
+    let mut n = build;
+
+    for suite in &run.suites {
+        let _ = writeln!(out, "# {}", suite.name);
+
+        for test in &suite.tests {
+            n += 1;
+
+            match test.status {
+                "pass" | "measured" => {
+                    let _ = writeln!(out, "ok {} - {}", n, test.name);
+                }
+                "ignored" => {
+                    let _ = writeln!(out, "ok {} - {} # SKIP", n, test.name);
+                }
+                _ => {
+                    let _ = writeln!(out, "not ok {} - {}", n, test.name);
+                    let message = match test.status {
+                        "incomplete" => "test did not finish",
+                        _ => test.error.unwrap_or(""),
+                    };
+                    out.push_str("  ---\n");
+                    let _ = writeln!(out, "  message: {}", serde_json::to_string(message).unwrap());
+                    out.push_str("  ...\n");
+                }
+            }
+        }
+    }
+
+    out
+}
+
+// Wraps `x` in enough backticks that none inside it end the code span.
+fn code_span(x: &str) -> String {
+    let longest = x.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest + 1);
+
+    if x.starts_with('`') || x.ends_with('`') {
+        format!("{} {} {}", fence, x, fence)
+    } else {
+        format!("{}{}{}", fence, x, fence)
+    }
+}
+
+fn markdown_section(out: &mut String, title: &str, tests: &[TestId]) {
+    if tests.is_empty() {
+        return;
+    }
+
+    let _ = writeln!(out, "\n**{} ({})**\n", title, tests.len());
+
+    for x in tests {
+        let _ = writeln!(out, "- {} in {}", code_span(x.name), code_span(x.suite));
+    }
+}
+
+fn markdown_durations(out: &mut String, changes: &[DurationChange]) {
+    if changes.is_empty() {
+        return;
+    }
+
+    let _ = writeln!(out, "\n**Slower ({})**\n", changes.len());
+
+    for x in changes {
+        let _ = writeln!(
+            out,
+            "- {} in {}: {:.3}s \u{2192} {:.3}s",
+            code_span(x.test.name),
+            code_span(x.test.suite),
+            x.old,
+            x.new
+        );
+    }
+}
+
+/// Renders the changes between two runs as Markdown, for a pull request
+/// comment.
+pub fn diff_to_markdown(diff: &RunDiff) -> String {
+    let mut out = String::from("### Test changes\n");
+
+    if diff.is_empty() {
+        out.push_str("\nNo tests changed.\n");
+        return out;
+    }
+
+    markdown_section(&mut out, "Newly failing", &diff.newly_failing);
+    markdown_section(&mut out, "Newly passing", &diff.newly_passing);
+    markdown_section(&mut out, "Newly ignored", &diff.newly_ignored);
+    markdown_section(&mut out, "Added", &diff.added);
+    markdown_section(&mut out, "Removed", &diff.removed);
+    markdown_durations(&mut out, &diff.slower);
+
+    out
+}
+
+pub fn to_summary(run: &TestRun) -> String {
+    let mut out = String::new();
+
+    if let Some(ref x) = run.build_failure {
+        let _ = writeln!(
+            out,
+            "build failed{}",
+            x.crate_name.as_ref().map_or(String::new(), |x| format!(": {}", x))
+        );
+        for d in &x.diagnostics {
+            match d.code {
+                Some(ref code) => {
+                    let _ = writeln!(out, "  error[{}]: {}", code, d.message);
+                }
+                None => {
+                    let _ = writeln!(out, "  error: {}", d.message);
+                }
+            }
+        }
+    }
+
+    let width = run.suites
+        .iter()
+        .map(|x| x.name.len())
+        .chain(Some("suite".len()))
+        .max()
+        .unwrap_or(0);
+
+    let _ = writeln!(
+        out,
+        "{:width$}  {:>6}  {:>6}  {:>7}  {:>6}",
+        "suite",
+        "passed",
+        "failed",
+        "ignored",
+        "total",
+        width = width
+    );
+
+    let mut totals = (0, 0, 0, 0);
+
+    for suite in &run.suites {
+        let _ = writeln!(
+            out,
+            "{:width$}  {:>6}  {:>6}  {:>7}  {:>6}",
+            suite.name,
+            suite.passed,
+            suite.failed,
+            suite.ignored,
+            suite.total,
+            width = width
+        );
+
+        totals.0 += suite.passed;
+        totals.1 += suite.failed;
+        totals.2 += suite.ignored;
+        totals.3 += suite.total;
+    }
+
+    let _ = writeln!(
+        out,
+        "{:width$}  {:>6}  {:>6}  {:>7}  {:>6}",
+        "total",
+        totals.0,
+        totals.1,
+        totals.2,
+        totals.3,
+        width = width
+    );
+
+    let failed: Vec<(&str, &Test)> = run.suites
+        .iter()
+        .flat_map(|s| s.tests.iter().map(move |t| (s.name, t)))
+        .filter(|&(_, t)| t.status == "fail" || t.status == "incomplete")
+        .collect();
+
+    if !failed.is_empty() {
+        out.push_str("\nfailures:\n");
+        for (suite, test) in failed {
+            let _ = writeln!(out, "    {} ({}) {}", test.name, test.status, suite);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{self, Value};
+
+    use super::{diff_to_markdown, to_json, to_junit, to_summary, to_tap};
+    use super::super::{cargo_test_result_parser, parsed, DurationChange, RunDiff, TestId, TestRun};
+
+    const OUTPUT: &[u8] = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 3 tests
+test tests::it_passes ... ok
+test tests::it_fails ... FAILED
+test tests::it_is_ignored ... ignored
+
+failures:
+
+---- tests::it_fails stdout ----
+thread 'tests::it_fails' panicked at 'left < right', src/lib.rs:10
+note: Run with `RUST_BACKTRACE=1` for a backtrace.
+
+
+failures:
+    tests::it_fails
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+
+    fn run() -> TestRun<'static> {
+        parsed(cargo_test_result_parser(OUTPUT))
+    }
+
+    #[test]
+    fn it_should_render_json() {
+        let value: Value = serde_json::from_str(&to_json(&run())).unwrap();
+        let suite = &value["suites"][0];
+
+        assert_eq!(suite["name"], "target/debug/deps/foo-5a7be5d1b9c8e0f6");
+        assert_eq!(suite["state"], "fail");
+        assert_eq!(suite["passed"], 1);
+        assert_eq!(suite["failed"], 1);
+        assert_eq!(suite["ignored"], 1);
+        assert_eq!(suite["total"], 3);
+        assert_eq!(suite["failure_names"], json!(["tests::it_fails"]));
+        assert_eq!(
+            suite["tests"][1],
+            json!({
+                "name": "tests::it_fails",
+                "status": "fail",
+                "error": "thread 'tests::it_fails' panicked at 'left < right', src/lib.rs:10",
+                "slow_warning": false,
+                "stdout": null,
+                "location": {
+                    "file": "src/lib.rs",
+                    "line": 10,
+                    "column": null,
+                },
+                "assertion": null,
+                "backtrace": [],
+                "failure_kind": { "kind": "panic" },
+                "duration": null,
+                "retries": 0,
+            })
+        );
+        assert_eq!(value["build_failure"], Value::Null);
+        assert_eq!(value["warnings"], json!([]));
+    }
+
+    #[test]
+    fn it_should_render_junit() {
+        assert_eq!(
+            to_junit(&run()),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<testsuites tests=\"3\" failures=\"1\" errors=\"0\">
+  <testsuite name=\"target/debug/deps/foo-5a7be5d1b9c8e0f6\" tests=\"3\" failures=\"1\" errors=\"0\" skipped=\"1\">
+    <testcase name=\"tests::it_passes\" classname=\"target/debug/deps/foo-5a7be5d1b9c8e0f6\"/>
+    <testcase name=\"tests::it_fails\" classname=\"target/debug/deps/foo-5a7be5d1b9c8e0f6\" file=\"src/lib.rs\" line=\"10\">
+      <failure message=\"thread &apos;tests::it_fails&apos; panicked at &apos;left &lt; right&apos;, src/lib.rs:10\">thread &apos;tests::it_fails&apos; panicked at &apos;left &lt; right&apos;, src/lib.rs:10</failure>
+    </testcase>
+    <testcase name=\"tests::it_is_ignored\" classname=\"target/debug/deps/foo-5a7be5d1b9c8e0f6\">
+      <skipped/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"
+        );
+    }
+
+    #[test]
+    fn it_should_render_tap() {
+        assert_eq!(
+            to_tap(&run()),
+            "TAP version 13
+1..3
+# target/debug/deps/foo-5a7be5d1b9c8e0f6
+ok 1 - tests::it_passes
+not ok 2 - tests::it_fails
+  ---
+  message: \"thread 'tests::it_fails' panicked at 'left < right', src/lib.rs:10\"
+  ...
+ok 3 - tests::it_is_ignored # SKIP
+"
+        );
+    }
+
+    #[test]
+    fn it_should_render_a_summary() {
+        assert_eq!(
+            to_summary(&run()),
+            "suite                                   passed  failed  ignored   total
+target/debug/deps/foo-5a7be5d1b9c8e0f6       1       1        1       3
+total                                        1       1        1       3
+
+failures:
+    tests::it_fails (fail) target/debug/deps/foo-5a7be5d1b9c8e0f6
+"
+        );
+    }
+
+    #[test]
+    fn it_should_render_a_diff() {
+        let id = |name| TestId {
+            suite: "demo",
+            name,
+        };
+
+        let diff = RunDiff {
+            newly_failing: vec![id("tests::breaks")],
+            newly_passing: vec![],
+            added: vec![id("tests::is_new"), id("tests::`odd`_name")],
+            removed: vec![],
+            newly_ignored: vec![],
+            slower: vec![DurationChange {
+                test: id("tests::slows_down"),
+                old: 0.2,
+                new: 0.9,
+            }],
+        };
+
+        assert_eq!(
+            diff_to_markdown(&diff),
+            "### Test changes
+
+**Newly failing (1)**
+
+- `tests::breaks` in `demo`
+
+**Added (2)**
+
+- `tests::is_new` in `demo`
+- ``tests::`odd`_name`` in `demo`
+
+**Slower (1)**
+
+- `tests::slows_down` in `demo`: 0.200s \u{2192} 0.900s
+"
+        );
+    }
+}
@@ -0,0 +1,802 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use std::mem;
+use std::str;
+
+use nom::IResult;
+
+use super::{any_line, attach_rerun_hints, build_item, handle_test_run, normalize, rerun_hint,
+            strip_should_panic, suite_count, suite_line, suite_parser, suite_result, targets_failed,
+            test_result, BuildItem, Suite, TestRun};
+
+/// The raw output of one suite, from its `Running` line up to its
+/// `test result:` line, or up to wherever it stopped if it crashed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuiteBlock {
+    text: Vec<u8>,
+}
+
+impl SuiteBlock {
+    pub fn text(&self) -> &[u8] {
+        &self.text
+    }
+
+    pub fn suite<'a>(&'a self) -> Option<Suite<'a, 'a, 'a, 'a, 'a>> {
+        match suite_parser(&self.text) {
+            IResult::Done(_, x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+named!(
+    build_items<Vec<BuildItem<'a>> >,
+    many0!(build_item)
+);
+
+named!(
+    rerun_hints<Vec<Vec<Vec<&'a str> > > >,
+    many0!(
+        alt_complete!(
+            map!(rerun_hint, |x| vec![x]) |
+            targets_failed |
+            map!(any_line, |_| vec![])
+        )
+    )
+);
+
+impl<'a> TestRun<'a> {
+    /// Puts a streamed run back together, as if the whole output had been
+    /// parsed at once. `build_output` is the text of every `BuildLine`, each
+    /// followed by a newline.
+    pub fn from_stream(build_output: &'a [u8], blocks: &'a [SuiteBlock]) -> TestRun<'a> {
+        let items = match build_items(build_output) {
+            IResult::Done(_, x) => x,
+            _ => vec![],
+        };
+
+        // The rerun hints for suites that finished normally come after their
+        // blocks, with the build lines.
+        let hints = match rerun_hints(build_output) {
+            IResult::Done(_, x) => x.into_iter().flatten().collect(),
+            _ => vec![],
+        };
+
+        let suites = attach_rerun_hints(blocks.iter().filter_map(|x| x.suite()).collect(), hints);
+
+        handle_test_run(items, suites)
+    }
+}
+
+// Cargo right-aligns its status words, which tells its own `Running` lines
+// apart from whatever a test printed to stderr.
+fn is_suite_header(line: &[u8]) -> bool {
+    line.starts_with(b"     Running ") || line.starts_with(b"   Doc-tests ")
+}
+
+/// Puts cargo's stderr and the test binaries' stdout, read from separate
+/// pipes, back in the order a terminal shows them, a line at a time as the
+/// lines arrive. Each `Running` or `Doc-tests` line on stderr is followed by
+/// the next suite on stdout, from its `running N tests` line on. A suite that
+/// shows up before its header waits for it, and stderr lines that arrive
+/// while a suite is running wait until it's done.
+#[derive(Debug, Default)]
+pub struct Interleaver {
+    /// Suites read from stdout whose header hasn't arrived yet.
+    queued: VecDeque<Vec<u8>>,
+    /// Headers whose suite hasn't started on stdout yet.
+    headers: usize,
+    /// Whether a suite has started and not yet printed its `test result:`.
+    open: bool,
+    held: Vec<u8>,
+}
+
+impl Interleaver {
+    pub fn new() -> Interleaver {
+        Interleaver::default()
+    }
+
+    /// Takes a line read from cargo's stderr, and returns what can be parsed
+    /// now.
+    pub fn stderr(&mut self, line: &[u8]) -> Vec<u8> {
+        let line = normalize(line);
+        let mut out = vec![];
+
+        if is_suite_header(&line) {
+            // The previous suite stopped without a summary.
+            self.open = false;
+            out.append(&mut self.held);
+            out.extend_from_slice(&line);
+
+            match self.queued.pop_front() {
+                Some(x) => self.emit(&x, &mut out),
+                None => self.headers += 1,
+            }
+        } else if self.open {
+            self.held.extend_from_slice(&line);
+        } else {
+            out.extend_from_slice(&line);
+        }
+
+        out
+    }
+
+    /// Takes a line read from the test binaries' stdout, and returns what can
+    /// be parsed now.
+    pub fn stdout(&mut self, line: &[u8]) -> Vec<u8> {
+        let line = normalize(line);
+        let mut out = vec![];
+
+        if suite_count(&line).is_done() {
+            if self.queued.is_empty() && self.headers > 0 {
+                self.headers -= 1;
+                self.emit(&line, &mut out);
+            } else {
+                self.queued.push_back(line.into_owned());
+            }
+        } else {
+            match self.queued.back_mut() {
+                Some(x) => x.extend_from_slice(&line),
+                None => self.emit(&line, &mut out),
+            }
+        }
+
+        out
+    }
+
+    /// Returns whatever is still waiting. Call this once both pipes have
+    /// closed.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut out = mem::take(&mut self.held);
+
+        for x in self.queued.drain(..) {
+            out.extend(x);
+        }
+
+        self.open = false;
+
+        out
+    }
+
+    fn emit(&mut self, text: &[u8], out: &mut Vec<u8>) {
+        for line in text.split_inclusive(|&c| c == b'\n') {
+            out.extend_from_slice(line);
+
+            if suite_count(line).is_done() {
+                self.open = true;
+            } else if self.open && line.starts_with(b"test result: ") {
+                self.open = false;
+                out.append(&mut self.held);
+            }
+        }
+    }
+}
+
+/// Interleaves the whole of cargo's stderr and the test binaries' stdout, as
+/// [`Interleaver`] does line by line.
+pub fn interleave(stderr: &[u8], stdout: &[u8]) -> Vec<u8> {
+    let mut order = Interleaver::new();
+    let mut out = vec![];
+
+    for line in stdout.split_inclusive(|&c| c == b'\n') {
+        out.extend(order.stdout(line));
+    }
+
+    for line in stderr.split_inclusive(|&c| c == b'\n') {
+        out.extend(order.stderr(line));
+    }
+
+    out.extend(order.finish());
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A line of output that isn't part of a suite, like `Compiling ...`.
+    BuildLine(String),
+    SuiteStarted { name: String, planned: i64 },
+    TestFinished { name: String, status: String },
+    SuiteFinished(SuiteBlock),
+}
+
+/// Parses `cargo test` output as it arrives, emitting events as soon as each
+/// line is complete.
+#[derive(Debug, Default)]
+pub struct StreamParser {
+    pending: Vec<u8>,
+    name: Option<String>,
+    block: Option<Vec<u8>>,
+    /// A test whose verdict will come on a later line, after its output.
+    running: Option<String>,
+    /// Whether the open suite's tests are being listed, between `running N
+    /// tests` and `test result:`. A `Running` line there is test output.
+    listing: bool,
+}
+
+impl StreamParser {
+    pub fn new() -> StreamParser {
+        StreamParser::default()
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Event> {
+        let mut events = vec![];
+
+        self.pending.extend_from_slice(chunk);
+
+        let mut start = 0;
+
+        while let Some(i) = self.pending[start..].iter().position(|&c| c == b'\n') {
+            let end = start + i + 1;
+            let line = self.pending[start..end].to_vec();
+
+            self.handle_line(line, &mut events);
+            start = end;
+        }
+
+        self.pending.drain(..start);
+
+        events
+    }
+
+    /// Flushes a trailing line without a newline and closes any open suite.
+    /// Call this once the output has ended.
+    pub fn finish(&mut self) -> Vec<Event> {
+        let mut events = vec![];
+
+        if !self.pending.is_empty() {
+            let line = mem::take(&mut self.pending);
+            self.handle_line(line, &mut events);
+        }
+
+        self.finish_suite(&mut events);
+
+        events
+    }
+
+    fn finish_suite(&mut self, events: &mut Vec<Event>) {
+        self.name = None;
+        self.running = None;
+        self.listing = false;
+
+        if let Some(text) = self.block.take() {
+            events.push(Event::SuiteFinished(SuiteBlock { text }));
+        }
+    }
+
+    fn handle_line(&mut self, line: Vec<u8>, events: &mut Vec<Event>) {
+        let line = match normalize(&line) {
+            Cow::Owned(x) => x,
+            Cow::Borrowed(_) => line,
+        };
+
+        // Cargo reports a test binary that crashed, which ends its test list.
+        if self.listing && String::from_utf8_lossy(&line).contains("error: test failed") {
+            self.listing = false;
+        }
+
+        if let (false, IResult::Done(_, name)) = (self.listing, suite_line(&line)) {
+            // A suite that never printed its result crashed; close it out.
+            self.finish_suite(events);
+            self.name = Some(name.to_string());
+            self.block = Some(line);
+            return;
+        }
+
+        let mut block = match self.block.take() {
+            Some(x) => x,
+            None => {
+                let text = String::from_utf8_lossy(&line);
+                events.push(Event::BuildLine(text.trim_end_matches('\n').to_string()));
+                return;
+            }
+        };
+
+        block.extend_from_slice(&line);
+        self.block = Some(block);
+
+        if let IResult::Done(_, planned) = suite_count(&line) {
+            self.listing = true;
+            events.push(Event::SuiteStarted {
+                name: self.name.clone().unwrap_or_default(),
+                planned,
+            });
+        } else if let IResult::Done(_, _) = suite_result(&line) {
+            self.finish_suite(events);
+        } else if let IResult::Done(_, x) = test_result(&line) {
+            if x.status == "incomplete" {
+                self.running = Some(x.name.to_string());
+            } else {
+                self.running = None;
+                events.push(Event::TestFinished {
+                    name: x.name.to_string(),
+                    status: x.status.to_string(),
+                });
+            }
+        } else if let Some(name) = started_test(&line) {
+            self.running = Some(name.to_string());
+        } else if self.running.is_some() {
+            let status = match String::from_utf8_lossy(&line).trim() {
+                "ok" => "pass",
+                "FAILED" => "fail",
+                _ => return,
+            };
+
+            events.push(Event::TestFinished {
+                name: self.running.take().unwrap_or_default(),
+                status: status.to_string(),
+            });
+        }
+    }
+}
+
+// With `--nocapture`, what a test prints can follow its name on the same
+// line, and its verdict comes on a later one.
+fn started_test(line: &[u8]) -> Option<&str> {
+    let line = str::from_utf8(line).ok()?.strip_prefix("test ")?;
+
+    line.find(" ... ").map(|i| strip_should_panic(&line[..i]))
+}
+
+/// Reads `cargo test` output from a `BufRead`, yielding each suite as soon
+/// as its `test result:` line is reached. Only the suite currently being read
+/// is held in memory, so arbitrarily large logs can be parsed from a pipe.
+///
+/// Items are `SuiteBlock`s rather than `Suite`s, since a `Suite` borrows from
+/// the text it was parsed from; call `SuiteBlock::suite` to get one.
+pub struct SuiteReader<R> {
+    reader: R,
+    parser: StreamParser,
+    ready: VecDeque<SuiteBlock>,
+    line: Vec<u8>,
+    done: bool,
+}
+
+impl<R: BufRead> SuiteReader<R> {
+    pub fn new(reader: R) -> SuiteReader<R> {
+        SuiteReader {
+            reader,
+            parser: StreamParser::new(),
+            ready: VecDeque::new(),
+            line: vec![],
+            done: false,
+        }
+    }
+
+    fn push(&mut self, events: Vec<Event>) {
+        for event in events {
+            if let Event::SuiteFinished(x) = event {
+                self.ready.push_back(x);
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for SuiteReader<R> {
+    type Item = io::Result<SuiteBlock>;
+
+    fn next(&mut self) -> Option<io::Result<SuiteBlock>> {
+        while self.ready.is_empty() && !self.done {
+            self.line.clear();
+
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => {
+                    self.done = true;
+                    let events = self.parser.finish();
+                    self.push(events);
+                }
+                Ok(_) => {
+                    let events = self.parser.feed(&self.line);
+                    self.push(events);
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        self.ready.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+
+    use super::{interleave, Event, Interleaver, StreamParser, SuiteReader};
+    use super::super::{cargo_test_result_parser, TestRun};
+
+    fn feed_in_chunks(output: &[u8], size: usize) -> Vec<Event> {
+        let mut parser = StreamParser::new();
+
+        let mut events: Vec<Event> = output
+            .chunks(size)
+            .flat_map(|x| parser.feed(x))
+            .collect();
+
+        events.extend(parser.finish());
+
+        events
+    }
+
+    #[test]
+    fn it_should_emit_events_as_lines_complete() {
+        let output = b"   Compiling foo v0.1.0 (file:///foo)
+    Finished debug [unoptimized + debuginfo] target(s) in 12.11 secs
+     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::it_passes ... ok
+test tests::it_fails ... FAILED
+
+failures:
+
+---- tests::it_fails stdout ----
+thread 'tests::it_fails' panicked at 'boom', src/lib.rs:10
+note: Run with `RUST_BACKTRACE=1` for a backtrace.
+
+
+failures:
+    tests::it_fails
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+
+error: test failed";
+
+        let events = feed_in_chunks(output, 7);
+
+        assert_eq!(
+            events[..5].to_vec(),
+            vec![
+                Event::BuildLine("   Compiling foo v0.1.0 (file:///foo)".to_string()),
+                Event::BuildLine(
+                    "    Finished debug [unoptimized + debuginfo] target(s) in 12.11 secs"
+                        .to_string()
+                ),
+                Event::SuiteStarted {
+                    name: "target/debug/deps/foo-5a7be5d1b9c8e0f6".to_string(),
+                    planned: 2,
+                },
+                Event::TestFinished {
+                    name: "tests::it_passes".to_string(),
+                    status: "pass".to_string(),
+                },
+                Event::TestFinished {
+                    name: "tests::it_fails".to_string(),
+                    status: "fail".to_string(),
+                },
+            ]
+        );
+
+        let suite = match events[5] {
+            Event::SuiteFinished(ref x) => x,
+            ref x => panic!("expected a finished suite, got {:?}", x),
+        };
+
+        assert_eq!(
+            events[6..].to_vec(),
+            vec![
+                Event::BuildLine("".to_string()),
+                Event::BuildLine("error: test failed".to_string()),
+            ]
+        );
+
+        let suite = suite.suite().unwrap();
+
+        assert_eq!(suite.name, "target/debug/deps/foo-5a7be5d1b9c8e0f6");
+        assert_eq!(suite.state, "fail");
+        assert_eq!(suite.passed, 1);
+        assert_eq!(suite.failed, 1);
+        assert_eq!(
+            suite.tests[1].error,
+            Some("thread 'tests::it_fails' panicked at 'boom', src/lib.rs:10")
+        );
+    }
+
+    #[test]
+    fn it_should_emit_the_same_events_for_crlf_output() {
+        let output = b"   Compiling foo v0.1.0 (file:///foo)
+     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 1 test
+test tests::it_passes ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+";
+
+        let crlf: Vec<u8> = output
+            .iter()
+            .flat_map(|&c| if c == b'\n' { vec![b'\r', c] } else { vec![c] })
+            .collect();
+
+        assert_eq!(feed_in_chunks(&crlf, 3), feed_in_chunks(output, 3));
+    }
+
+    #[test]
+    fn it_should_finish_a_test_after_its_output() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::it_prints ... hello
+ok
+test tests::it_passes ... ok
+
+test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+";
+
+        let finished: Vec<Event> = feed_in_chunks(output, 4)
+            .into_iter()
+            .filter(|x| matches!(*x, Event::TestFinished { .. }))
+            .collect();
+
+        assert_eq!(
+            finished,
+            vec![
+                Event::TestFinished {
+                    name: "tests::it_prints".to_string(),
+                    status: "pass".to_string(),
+                },
+                Event::TestFinished {
+                    name: "tests::it_passes".to_string(),
+                    status: "pass".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_close_a_crashed_suite() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::it_passes ... ok
+test tests::it_segfaults ... error: test failed, to rerun pass '--lib'
+     Running target/debug/integration_test-283604d1063344ba
+
+running 0 tests
+
+test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+";
+
+        let suites: Vec<_> = feed_in_chunks(output, 1)
+            .into_iter()
+            .filter_map(|x| match x {
+                Event::SuiteFinished(x) => Some(x),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(suites.len(), 2);
+
+        let crashed = suites[0].suite().unwrap();
+        assert_eq!(crashed.state, "fail");
+        assert_eq!(crashed.tests.len(), 2);
+        assert_eq!(crashed.tests[1].status, "incomplete");
+
+        let next = suites[1].suite().unwrap();
+        assert_eq!(next.name, "target/debug/integration_test-283604d1063344ba");
+        assert_eq!(next.state, "pass");
+    }
+
+    #[test]
+    fn it_should_read_suites_from_a_buf_read() {
+        let output = &b"   Compiling foo v0.1.0 (file:///foo)
+     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 0 tests
+
+test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+     Running target/debug/integration_test-283604d1063344ba
+
+running 1 test
+test it_runs_a_command ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+   Doc-tests foo
+
+running 0 tests
+
+test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out"[..];
+
+        let blocks = SuiteReader::new(output)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let names: Vec<&str> = blocks.iter().map(|x| x.suite().unwrap().name).collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "target/debug/deps/foo-5a7be5d1b9c8e0f6",
+                "target/debug/integration_test-283604d1063344ba",
+                "foo",
+            ]
+        );
+
+        assert_eq!(blocks[1].suite().unwrap().tests[0].name, "it_runs_a_command");
+    }
+
+    #[test]
+    fn it_should_rebuild_a_run_from_events() {
+        let output = b"   Compiling foo v0.1.0 (file:///foo)
+warning: unused variable: `x`
+ --> src/lib.rs:3:9
+  |
+3 |     let x = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+warning: `foo` (lib test) generated 1 warning
+    Finished test [unoptimized + debuginfo] target(s) in 0.52s
+     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 2 tests
+test tests::it_passes ... ok
+test tests::it_fails ... FAILED
+
+failures:
+
+---- tests::it_fails stdout ----
+thread 'tests::it_fails' panicked at 'boom', src/lib.rs:10
+
+
+failures:
+    tests::it_fails
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+
+error: test failed, to rerun pass `--lib`
+";
+
+        let mut build_output = vec![];
+        let mut blocks = vec![];
+
+        for event in feed_in_chunks(output, 5) {
+            match event {
+                Event::BuildLine(x) => {
+                    build_output.extend_from_slice(x.as_bytes());
+                    build_output.push(b'\n');
+                }
+                Event::SuiteFinished(x) => blocks.push(x),
+                _ => {}
+            }
+        }
+
+        let run = TestRun::from_stream(&build_output, &blocks);
+
+        match cargo_test_result_parser(output) {
+            IResult::Done(_, x) => assert_eq!(run, x),
+            x => panic!("expected the output to parse, got {:?}", x),
+        }
+
+        assert_eq!(run.build_failure, None);
+        assert_eq!(run.warnings.len(), 1);
+        assert_eq!(run.suites.len(), 1);
+    }
+
+    #[test]
+    fn it_should_interleave_cargo_and_test_output() {
+        let stderr = b"   Compiling foo v0.1.0 (file:///foo)
+    Finished test [unoptimized + debuginfo] target(s) in 0.52s
+     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+Running migrations
+     Running target/debug/integration_test-283604d1063344ba
+error: test failed, to rerun pass `--test integration_test`
+";
+
+        let stdout = b"
+running 1 test
+test tests::it_passes ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+
+running 2 tests
+test it_runs_a_command ... ok
+test it_crashes ... ";
+
+        assert_eq!(
+            String::from_utf8(interleave(stderr, stdout)).unwrap(),
+            "
+   Compiling foo v0.1.0 (file:///foo)
+    Finished test [unoptimized + debuginfo] target(s) in 0.52s
+     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+running 1 test
+test tests::it_passes ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+
+
+Running migrations
+     Running target/debug/integration_test-283604d1063344ba
+running 2 tests
+test it_runs_a_command ... ok
+test it_crashes ... error: test failed, to rerun pass `--test integration_test`
+"
+        );
+    }
+
+    #[test]
+    fn it_should_interleave_lines_as_they_arrive() {
+        let mut order = Interleaver::new();
+        let mut parser = StreamParser::new();
+
+        // As read from the two pipes: the second suite starts on stdout
+        // before its header shows up on stderr.
+        let lines: Vec<(bool, &[u8])> = vec![
+            (false, b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6\n"),
+            (true, b"\n"),
+            (true, b"running 1 test\n"),
+            (false, b"connecting\n"),
+            (true, b"test tests::it_passes ... ok\n"),
+            (true, b"\n"),
+            (true, b"test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out\n"),
+            (true, b"\n"),
+            (true, b"running 1 test\n"),
+            (true, b"test it_runs_a_command ... ok\n"),
+            (false, b"     Running target/debug/integration_test-283604d1063344ba\n"),
+            (true, b"\n"),
+            (true, b"test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out\n"),
+        ];
+
+        let mut finished = vec![];
+        let mut build_lines = vec![];
+
+        for (i, (is_stdout, line)) in lines.into_iter().enumerate() {
+            let ready = if is_stdout { order.stdout(line) } else { order.stderr(line) };
+
+            for event in parser.feed(&ready) {
+                match event {
+                    Event::SuiteFinished(x) => finished.push((i, x.suite().unwrap().name.to_string())),
+                    Event::BuildLine(x) => build_lines.push(x),
+                    _ => {}
+                }
+            }
+        }
+
+        assert!(order.finish().is_empty());
+        assert!(parser.finish().is_empty());
+
+        assert_eq!(
+            finished,
+            vec![
+                (6, "target/debug/deps/foo-5a7be5d1b9c8e0f6".to_string()),
+                (12, "target/debug/integration_test-283604d1063344ba".to_string()),
+            ]
+        );
+        assert!(build_lines.contains(&"connecting".to_string()));
+    }
+
+    #[test]
+    fn it_should_not_start_a_suite_in_test_output() {
+        let output = b"     Running target/debug/deps/foo-5a7be5d1b9c8e0f6
+
+running 1 test
+test tests::db ... 
+Running migrations
+ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+";
+
+        let suites: Vec<_> = feed_in_chunks(output, 6)
+            .into_iter()
+            .filter_map(|x| match x {
+                Event::SuiteFinished(x) => Some(x),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(suites.len(), 1);
+
+        let suite = suites[0].suite().unwrap();
+        assert_eq!(suite.state, "pass");
+        assert_eq!(suite.tests[0].status, "pass");
+        assert_eq!(suite.tests[0].stdout, Some("Running migrations"));
+    }
+}